@@ -0,0 +1,445 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    cell::{Cell, NumberFormat},
+    infer::parse_number,
+    row::Row,
+    sort::SortKey,
+    table::Table,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Average,
+    /// Number of different non-empty values.
+    Distinct,
+}
+
+impl AggregateFunction {
+    pub const ALL: [AggregateFunction; 6] = [
+        AggregateFunction::Count,
+        AggregateFunction::Sum,
+        AggregateFunction::Min,
+        AggregateFunction::Max,
+        AggregateFunction::Average,
+        AggregateFunction::Distinct,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+            AggregateFunction::Average => "avg",
+            AggregateFunction::Distinct => "distinct",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        AggregateFunction::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "Count",
+            AggregateFunction::Sum => "Sum",
+            AggregateFunction::Min => "Min",
+            AggregateFunction::Max => "Max",
+            AggregateFunction::Average => "Average",
+            AggregateFunction::Distinct => "Distinct count",
+        }
+    }
+}
+
+/// A value computed over the rows of a group, e.g. the sum of a column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    /// Heading or 1-based index of the column. Counting without a column
+    /// counts rows.
+    pub column: Option<String>,
+}
+
+impl Default for Aggregate {
+    fn default() -> Aggregate {
+        Aggregate {
+            function: AggregateFunction::Count,
+            column: None,
+        }
+    }
+}
+
+impl Aggregate {
+    /// Parses `count` or `FUNCTION:COLUMN`, where the function is one of
+    /// `count`, `sum`, `min`, `max`, `avg` or `distinct`.
+    pub fn parse(spec: &str) -> Result<Aggregate, String> {
+        let (name, column) = match spec.split_once(':') {
+            Some((f, c)) => (f, Some(c.trim().to_string())),
+            None => (spec, None),
+        };
+
+        let Some(function) = AggregateFunction::from_name(name) else {
+            return Err(format!("unknown aggregate function \"{}\"", name.trim()));
+        };
+        if column.is_none() && function != AggregateFunction::Count {
+            return Err(format!(
+                "{} needs a column, e.g. {}:COLUMN",
+                function.name(),
+                function.name()
+            ));
+        }
+
+        Ok(Aggregate { function, column })
+    }
+
+    /// Parses a comma-separated list of aggregates.
+    pub fn parse_list(list: &str) -> Result<Vec<Aggregate>, String> {
+        list.split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(Aggregate::parse)
+            .collect()
+    }
+
+    pub fn name(&self) -> String {
+        match &self.column {
+            Some(c) => format!("{}:{}", self.function.name(), c),
+            None => self.function.name().to_string(),
+        }
+    }
+
+    fn heading(&self) -> String {
+        match &self.column {
+            Some(c) => format!("{} of {}", self.function.label(), c),
+            None => self.function.label().to_string(),
+        }
+    }
+
+    fn column_index(&self, table: &Table) -> Result<Option<usize>, String> {
+        match &self.column {
+            Some(c) => match table.column_index(c) {
+                Some(i) => Ok(Some(i)),
+                None => Err(format!("unknown column \"{}\"", c)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Computes the aggregate over some rows, reading the column at `index`.
+    pub(crate) fn compute(&self, rows: &[&Row], index: Option<usize>) -> Cell {
+        let cells: Vec<&Cell> = match index {
+            Some(i) => rows
+                .iter()
+                .filter_map(|r| r.cells.get(i))
+                .filter(|c| !c.to_string().trim().is_empty())
+                .collect(),
+            None => return Cell::Number(rows.len() as f64),
+        };
+        let numbers: Vec<(f64, Option<NumberFormat>)> =
+            cells.iter().filter_map(|c| cell_number(c)).collect();
+        // Summing from -0.0, as `Sum` does, would show an empty sum as "-0"
+        let total = numbers.iter().fold(0.0, |total, (n, _)| total + n);
+        // Sums and averages keep the format of amounts, e.g. a currency
+        let format = numbers.first().and_then(|(_, f)| f.clone());
+        let formatted = |n: f64| match &format {
+            Some(f) => Cell::FormattedNumber(n, f.clone()),
+            None => Cell::Number(n),
+        };
+        let ordered = |ordering: Ordering| {
+            let key = SortKey::new("");
+            cells
+                .iter()
+                .map(|c| (key.sort_value(Some(c)), *c))
+                .reduce(|a, b| match b.0.cmp(&a.0) == ordering {
+                    true => b,
+                    false => a,
+                })
+                .map_or(Cell::Empty, |(_, c)| plain_cell(c))
+        };
+
+        match self.function {
+            AggregateFunction::Count => Cell::Number(cells.len() as f64),
+            AggregateFunction::Sum => formatted(total),
+            AggregateFunction::Average if numbers.is_empty() => Cell::Empty,
+            AggregateFunction::Average => formatted(total / numbers.len() as f64),
+            AggregateFunction::Min => ordered(Ordering::Less),
+            AggregateFunction::Max => ordered(Ordering::Greater),
+            AggregateFunction::Distinct => {
+                let mut values: Vec<String> = cells.iter().map(|c| c.to_string()).collect();
+                values.sort();
+                values.dedup();
+                Cell::Number(values.len() as f64)
+            }
+        }
+    }
+}
+
+/// Copies a cell for a summary sheet, where formulas referring to the
+/// source's columns would be wrong, so only their values are kept.
+fn plain_cell(cell: &Cell) -> Cell {
+    match cell {
+        Cell::Formula(_, v) => v.as_ref().clone(),
+        c => c.clone(),
+    }
+}
+
+/// Reads a cell as a number, with its format if it is an amount.
+pub(crate) fn cell_number(cell: &Cell) -> Option<(f64, Option<NumberFormat>)> {
+    match cell {
+        Cell::Number(n) => Some((*n, None)),
+        Cell::FormattedNumber(n, f @ (NumberFormat::Currency(_) | NumberFormat::Grouped(_))) => {
+            Some((*n, Some(f.clone())))
+        }
+        Cell::FormattedNumber(n, _) => Some((*n, None)),
+        Cell::Text(s) => parse_number(s.trim()).map(|n| (n, None)),
+        Cell::Formula(_, v) => cell_number(v),
+        _ => None,
+    }
+}
+
+/// Splits rows into groups with the same values in the given columns,
+/// ordered by those values. Each group comes with the cells of its first row.
+fn group_rows<'a>(rows: &[&'a Row], indices: &[usize]) -> Vec<(Vec<Cell>, Vec<&'a Row>)> {
+    let mut groups: Vec<(Vec<Cell>, Vec<&Row>)> = Vec::new();
+    let mut positions: HashMap<Vec<String>, usize> = HashMap::new();
+
+    for row in rows {
+        let cells: Vec<Cell> = indices
+            .iter()
+            .map(|i| row.cells.get(*i).map_or(Cell::Empty, plain_cell))
+            .collect();
+        let key: Vec<String> = cells.iter().map(|c| c.to_string()).collect();
+
+        match positions.get(&key) {
+            Some(p) => groups[*p].1.push(row),
+            None => {
+                positions.insert(key, groups.len());
+                groups.push((cells, vec![row]));
+            }
+        }
+    }
+
+    let key = SortKey::new("");
+    groups.sort_by(|(a, _), (b, _)| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| key.compare(&key.sort_value(Some(a)), &key.sort_value(Some(b))))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    groups
+}
+
+fn column_indices(table: &Table, columns: &[String]) -> Result<Vec<usize>, String> {
+    columns
+        .iter()
+        .map(|c| {
+            table
+                .column_index(c)
+                .ok_or(format!("unknown column \"{}\"", c))
+        })
+        .collect()
+}
+
+/// Headings of columns in a summary: the source table's, or the references
+/// used if it has none.
+fn source_headings(table: &Table, columns: &[String], indices: &[usize]) -> Vec<String> {
+    columns
+        .iter()
+        .zip(indices)
+        .map(|(c, i)| {
+            table
+                .headings
+                .as_ref()
+                .and_then(|h| h.get(*i).cloned())
+                .unwrap_or(c.clone())
+        })
+        .collect()
+}
+
+/// One row per distinct combination of values in some columns, with
+/// aggregates over the rows having it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GroupBy {
+    pub columns: Vec<String>,
+    pub aggregates: Vec<Aggregate>,
+}
+
+impl GroupBy {
+    /// Parses `COLUMN[,COLUMN...][=AGGREGATE[,AGGREGATE...]]`, counting rows
+    /// if no aggregates are given.
+    pub fn parse(spec: &str) -> Result<GroupBy, String> {
+        let (columns, aggregates) = match spec.split_once('=') {
+            Some((c, a)) => (c, Aggregate::parse_list(a)?),
+            None => (spec, vec![Aggregate::default()]),
+        };
+
+        Ok(GroupBy {
+            columns: columns
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+            aggregates,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        format!(
+            "{}={}",
+            self.columns.join(","),
+            self.aggregates
+                .iter()
+                .map(|a| a.name())
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+
+    pub fn to_table(&self, table: &Table) -> Result<Table, String> {
+        if self.columns.is_empty() {
+            return Err("expected at least one column to group by".to_string());
+        }
+
+        let indices = column_indices(table, &self.columns)?;
+        let aggregate_indices = self
+            .aggregates
+            .iter()
+            .map(|a| a.column_index(table))
+            .collect::<Result<Vec<Option<usize>>, String>>()?;
+        let rows: Vec<&Row> = table.rows.iter().collect();
+
+        let mut summary = Table::from_rows(
+            group_rows(&rows, &indices)
+                .into_iter()
+                .map(|(mut cells, rows)| {
+                    cells.extend(
+                        self.aggregates
+                            .iter()
+                            .zip(&aggregate_indices)
+                            .map(|(a, i)| a.compute(&rows, *i)),
+                    );
+                    Row::from_cells(cells)
+                })
+                .collect(),
+        );
+
+        if table.headings.is_some() {
+            let mut headings = source_headings(table, &self.columns, &indices);
+            headings.extend(self.aggregates.iter().map(|a| a.heading()));
+            summary.headings = Some(headings);
+        }
+
+        Ok(summary)
+    }
+}
+
+/// A cross-tabulation: one row per value of one column, one column per value
+/// of another, and an aggregate over the rows having both in each cell.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pivot {
+    pub rows: String,
+    pub columns: String,
+    pub value: Aggregate,
+}
+
+impl Pivot {
+    /// Parses `ROWS,COLUMNS[=AGGREGATE]`, counting rows if no aggregate is
+    /// given.
+    pub fn parse(spec: &str) -> Result<Pivot, String> {
+        let (columns, value) = match spec.split_once('=') {
+            Some((c, a)) => (c, Aggregate::parse(a)?),
+            None => (spec, Aggregate::default()),
+        };
+
+        match columns.split_once(',') {
+            Some((rows, columns)) if !rows.trim().is_empty() && !columns.trim().is_empty() => {
+                Ok(Pivot {
+                    rows: rows.trim().to_string(),
+                    columns: columns.trim().to_string(),
+                    value,
+                })
+            }
+            _ => Err(format!(
+                "expected ROWS,COLUMNS[=AGGREGATE], got \"{}\"",
+                spec
+            )),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{},{}={}", self.rows, self.columns, self.value.name())
+    }
+
+    pub fn to_table(&self, table: &Table) -> Result<Table, String> {
+        let indices = column_indices(table, &[self.rows.clone(), self.columns.clone()])?;
+        let value_index = self.value.column_index(table)?;
+        let rows: Vec<&Row> = table.rows.iter().collect();
+
+        let columns = group_rows(&rows, &indices[1..]);
+
+        let mut pivot = Table::from_rows(
+            group_rows(&rows, &indices[..1])
+                .into_iter()
+                .map(|(mut cells, rows)| {
+                    let groups = group_rows(&rows, &indices[1..]);
+
+                    cells.extend(columns.iter().map(|(column, _)| {
+                        match groups
+                            .iter()
+                            .find(|(c, _)| c[0].to_string() == column[0].to_string())
+                        {
+                            Some((_, rows)) => self.value.compute(rows, value_index),
+                            None => Cell::Empty,
+                        }
+                    }));
+                    Row::from_cells(cells)
+                })
+                .collect(),
+        );
+
+        if table.headings.is_some() {
+            let mut headings =
+                source_headings(table, std::slice::from_ref(&self.rows), &indices[..1]);
+            headings.extend(columns.iter().map(|(c, _)| match c[0].to_string() {
+                s if s.trim().is_empty() => "(empty)".to_string(),
+                s => s,
+            }));
+            pivot.headings = Some(headings);
+        }
+
+        Ok(pivot)
+    }
+}
+
+/// A summary of a worksheet's rows, written to a sheet of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Summary {
+    Group(GroupBy),
+    Pivot(Pivot),
+}
+
+impl Summary {
+    pub fn to_table(&self, table: &Table) -> Result<Table, String> {
+        match self {
+            Summary::Group(g) => g.to_table(table),
+            Summary::Pivot(p) => p.to_table(table),
+        }
+    }
+
+    /// What follows the title of the worksheet the summary summarises in
+    /// the name of its sheet, e.g. "by Region".
+    pub fn sheet_suffix(&self) -> String {
+        match self {
+            Summary::Group(g) => format!("by {}", g.columns.join(", ")),
+            Summary::Pivot(p) => format!("by {} and {}", p.rows, p.columns),
+        }
+    }
+}
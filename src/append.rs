@@ -0,0 +1,182 @@
+use crate::{cell::Cell, row::Row, table::Table};
+
+/// Heading of the column naming the source of each appended row.
+pub const SOURCE_HEADING: &str = "Source";
+
+/// Concatenates the rows of tables, each paired with the name of its source,
+/// lining up columns by heading (ignoring case) or, for tables without
+/// headings, by position. Columns missing from a table are left empty, and
+/// the source names are added as a last column if asked.
+pub fn append_tables(tables: Vec<(String, Table)>, source_column: bool) -> Table {
+    let mut headings: Option<Vec<String>> = None;
+    let mut rows = Vec::new();
+    let mut preamble = None;
+
+    for (source, table) in tables {
+        let width = table.width();
+
+        // Output column of each of the table's columns
+        let indices: Vec<usize> = match (&table.headings, headings.as_mut()) {
+            (Some(table_headings), Some(headings)) => {
+                let mut indices: Vec<usize> = Vec::new();
+
+                for i in 0..width {
+                    let heading = table_headings.get(i).cloned().unwrap_or_default();
+                    let found = headings.iter().enumerate().position(|(j, h)| {
+                        h.eq_ignore_ascii_case(&heading) && !indices.contains(&j)
+                    });
+
+                    indices.push(found.unwrap_or_else(|| {
+                        headings.push(heading);
+                        headings.len() - 1
+                    }));
+                }
+
+                indices
+            }
+            (Some(table_headings), None) => {
+                let mut table_headings = table_headings.clone();
+                table_headings.resize(width, String::new());
+                headings = Some(table_headings);
+
+                (0..width).collect()
+            }
+            (None, _) => (0..width).collect(),
+        };
+
+        for row in table.rows {
+            let mut cells = Vec::new();
+
+            for (i, cell) in indices.iter().zip(row.cells) {
+                if cells.len() <= *i {
+                    cells.resize(*i + 1, Cell::Empty);
+                }
+                cells[*i] = cell;
+            }
+
+            rows.push((
+                source.clone(),
+                Row {
+                    cells,
+                    style: row.style,
+                },
+            ));
+        }
+
+        preamble.get_or_insert(table.preamble);
+    }
+
+    let width = rows
+        .iter()
+        .map(|(_, r)| r.cells.len())
+        .chain(headings.iter().map(|h| h.len()))
+        .max()
+        .unwrap_or(0);
+
+    let rows = rows
+        .into_iter()
+        .map(|(source, mut row)| {
+            if source_column {
+                row.cells.resize(width, Cell::Empty);
+                row.cells.push(Cell::Text(source));
+            }
+            row
+        })
+        .collect();
+
+    if source_column {
+        if let Some(h) = headings.as_mut() {
+            h.resize(width, String::new());
+            h.push(SOURCE_HEADING.to_string());
+        }
+    }
+
+    Table {
+        preamble: preamble.unwrap_or_default(),
+        headings,
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(headings: Option<Vec<&str>>, rows: &[&[&str]]) -> Table {
+        let table = Table::from_rows(
+            rows.iter()
+                .map(|r| Row::from_cells(r.iter().map(|c| Cell::Text(c.to_string())).collect()))
+                .collect(),
+        );
+
+        match headings {
+            Some(h) => table.with_headings(h),
+            None => table,
+        }
+    }
+
+    fn text(table: &Table) -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|r| {
+                r.cells
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines_up_columns_by_heading() {
+        let appended = append_tables(
+            vec![
+                (
+                    "a.csv".to_string(),
+                    table(Some(vec!["Name", "Age"]), &[&["Ann", "30"]]),
+                ),
+                (
+                    "b.csv".to_string(),
+                    table(Some(vec!["age", "City", "Name"]), &[&["41", "Rome", "Bob"]]),
+                ),
+            ],
+            true,
+        );
+
+        assert_eq!(
+            appended.headings.clone().unwrap(),
+            ["Name", "Age", "City", SOURCE_HEADING]
+        );
+        assert_eq!(text(&appended), ["Ann|30||a.csv", "Bob|41|Rome|b.csv"]);
+    }
+
+    #[test]
+    fn repeated_headings_fill_separate_columns() {
+        let appended = append_tables(
+            vec![
+                ("1".to_string(), table(Some(vec!["X", "X"]), &[&["a", "b"]])),
+                ("2".to_string(), table(Some(vec!["X", "X"]), &[&["c", "d"]])),
+            ],
+            false,
+        );
+
+        assert_eq!(appended.headings.clone().unwrap(), ["X", "X"]);
+        assert_eq!(text(&appended), ["a|b", "c|d"]);
+    }
+
+    #[test]
+    fn lines_up_columns_by_position_without_headings() {
+        let appended = append_tables(
+            vec![
+                ("1".to_string(), table(None, &[&["a"], &["b", "c"]])),
+                ("2".to_string(), table(None, &[&["d", "e", "f"]])),
+            ],
+            true,
+        );
+
+        assert!(appended.headings.is_none());
+        assert_eq!(text(&appended), ["a|||1", "b|c||1", "d|e|f|2"]);
+    }
+}
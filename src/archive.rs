@@ -0,0 +1,550 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use ignore::overrides::Override;
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    directory::{DirectoryEntry, DirectoryOptions},
+    hash::{HashAlgorithm, HashingReader},
+};
+
+/// A kind of archive whose entries can be listed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+}
+
+impl ArchiveKind {
+    /// Kind of archive a file is by its name, e.g. `bundle.tar.gz`, ignoring
+    /// case.
+    pub fn from_path(path: &Path) -> Option<ArchiveKind> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+
+        [
+            (".zip", ArchiveKind::Zip),
+            (".tar", ArchiveKind::Tar),
+            (".tar.gz", ArchiveKind::TarGz),
+            (".tgz", ArchiveKind::TarGz),
+            (".tar.xz", ArchiveKind::TarXz),
+            (".txz", ArchiveKind::TarXz),
+        ]
+        .into_iter()
+        .find(|(ext, _)| name.ends_with(ext))
+        .map(|(_, kind)| kind)
+    }
+}
+
+/// What an archive records of one of its entries, in place of file metadata.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveMember {
+    /// Size of a zip entry's compressed data.
+    pub compressed_size: Option<u64>,
+    pub modified: Option<NaiveDateTime>,
+    /// Unix file mode, including the permissions.
+    pub mode: Option<u32>,
+    /// User name, or else ID, of a tar entry's owner.
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub link_target: Option<PathBuf>,
+}
+
+/// Largest zip in an archive whose entries are listed, as a zip is read from
+/// memory rather than as it is decompressed.
+const NESTED_ZIP_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Path of an entry of the archive at `archive`, leaving out any `.` and
+/// `..` in its name.
+fn member_path(archive: &Path, name: &Path) -> PathBuf {
+    let mut path = archive.to_path_buf();
+    path.extend(
+        name.components()
+            .filter(|c| matches!(c, Component::Normal(_))),
+    );
+    path
+}
+
+/// Lists the entries of archives as entries of a directory listing, with
+/// paths continuing from the archive's own, e.g. `bundle.zip/docs/a.txt`.
+pub(crate) struct ArchiveLister<'a> {
+    root: &'a Path,
+    options: &'a DirectoryOptions,
+    overrides: &'a Override,
+    /// Hashes of the contents of files, computed as they are read.
+    algorithms: Vec<HashAlgorithm>,
+}
+
+impl<'a> ArchiveLister<'a> {
+    pub(crate) fn new(
+        root: &'a Path,
+        options: &'a DirectoryOptions,
+        overrides: &'a Override,
+    ) -> ArchiveLister<'a> {
+        ArchiveLister {
+            root,
+            options,
+            overrides,
+            algorithms: options.content_hashes(),
+        }
+    }
+
+    /// Lists the entries of an archive file, or an error entry if it can't
+    /// be read.
+    pub(crate) fn list_file(
+        &self,
+        path: &Path,
+        kind: ArchiveKind,
+        entries: &mut Vec<DirectoryEntry>,
+    ) {
+        if let Err(e) = self.list_path(path, kind, entries) {
+            entries.push(self.error(path, e));
+        }
+    }
+
+    /// Lists the entries of an archive file, failing if it can't be read.
+    pub(crate) fn list_path(
+        &self,
+        path: &Path,
+        kind: ArchiveKind,
+        entries: &mut Vec<DirectoryEntry>,
+    ) -> Result<(), String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        self.list(path, kind, BufReader::new(file), entries)
+    }
+
+    fn error(&self, path: &Path, error: String) -> DirectoryEntry {
+        let mut entry = DirectoryEntry::new(self.root, path.to_path_buf());
+        entry.error = Some(error);
+        entry
+    }
+
+    /// Lists the entries of an archive at `path`, read from `reader`.
+    fn list<R: Read + Seek>(
+        &self,
+        path: &Path,
+        kind: ArchiveKind,
+        reader: R,
+        entries: &mut Vec<DirectoryEntry>,
+    ) -> Result<(), String> {
+        match kind {
+            ArchiveKind::Zip => self.list_zip(path, reader, entries),
+            kind => self.list_stream(path, kind, reader, entries),
+        }
+    }
+
+    /// Lists the entries of a tar archive at `path` as they are read from
+    /// `reader`, which zip archives can't be.
+    fn list_stream<R: Read>(
+        &self,
+        path: &Path,
+        kind: ArchiveKind,
+        reader: R,
+        entries: &mut Vec<DirectoryEntry>,
+    ) -> Result<(), String> {
+        match kind {
+            ArchiveKind::Zip => Err("zip archives can't be read as a stream".to_string()),
+            ArchiveKind::Tar => self.list_tar(path, reader, entries),
+            ArchiveKind::TarGz => {
+                self.list_tar(path, flate2::read::MultiGzDecoder::new(reader), entries)
+            }
+            ArchiveKind::TarXz => self.list_tar(path, xz2::read::XzDecoder::new(reader), entries),
+        }
+    }
+
+    /// Whether an entry of the archive at `archive` is left out by the
+    /// options, as the walk of a directory would leave it out.
+    fn excluded(&self, archive: &Path, path: &Path, directory: bool) -> bool {
+        let relative = |p: &Path| p.strip_prefix(self.root).unwrap_or(p).to_path_buf();
+        let matched =
+            |p: &Path, directory: bool| self.overrides.matched(relative(p), directory).is_ignore();
+        let too_deep = self
+            .options
+            .max_depth
+            .is_some_and(|max| relative(path).components().count() > max);
+
+        // Directories left out leave out all they contain
+        path.ancestors()
+            .skip(1)
+            .take_while(|a| *a != archive)
+            .any(|a| matched(a, true))
+            || matched(path, directory)
+            // Directory sizes count the files below the deepest level listed
+            || (!self.options.directories && (directory || too_deep))
+    }
+
+    fn entry(
+        &self,
+        path: PathBuf,
+        directory: bool,
+        symlink: bool,
+        size: Option<u64>,
+        member: ArchiveMember,
+    ) -> DirectoryEntry {
+        let mut entry = DirectoryEntry::new(self.root, path);
+        entry.directory = directory;
+        entry.symlink = symlink;
+        entry.size = size;
+        entry.archived = Some(member);
+        entry
+    }
+
+    /// Kind of archive a file in an archive is, if its entries are listed
+    /// too.
+    fn nested_kind(&self, entry: &DirectoryEntry) -> Option<ArchiveKind> {
+        match self.options.archive_contents && !entry.directory && !entry.symlink {
+            true => ArchiveKind::from_path(&entry.path),
+            false => None,
+        }
+    }
+
+    /// Hashes still to compute of a file in an archive.
+    fn missing_hashes(&self, entry: &DirectoryEntry) -> Vec<HashAlgorithm> {
+        self.algorithms
+            .iter()
+            .filter(|a| !entry.hashes.iter().any(|(h, _)| h == *a))
+            .copied()
+            .collect()
+    }
+
+    /// Whether the contents of a file in an archive are to be read.
+    fn wants_contents(&self, entry: &DirectoryEntry) -> bool {
+        !entry.directory
+            && !entry.symlink
+            && (!self.missing_hashes(entry).is_empty() || self.nested_kind(entry).is_some())
+    }
+
+    /// Adds a file of an archive, hashing its contents, followed by its own
+    /// entries if it is an archive too.
+    fn read_contents(
+        &self,
+        entry: DirectoryEntry,
+        reader: &mut dyn Read,
+        entries: &mut Vec<DirectoryEntry>,
+    ) {
+        let algorithms = self.missing_hashes(&entry);
+        let kind = self.nested_kind(&entry);
+        let size = entry.size.unwrap_or_default();
+        let path = entry.path.clone();
+        let index = entries.len();
+        entries.push(entry);
+
+        // The contents are hashed as the entries are listed from them
+        let mut reader = HashingReader::new(reader, &algorithms);
+        let listed = match kind {
+            None => Ok(()),
+            Some(ArchiveKind::Zip) if size > NESTED_ZIP_SIZE => Err(format!(
+                "entries not listed, as the zip is larger than {} MiB",
+                NESTED_ZIP_SIZE / 1024 / 1024
+            )),
+            Some(ArchiveKind::Zip) => {
+                let mut data = Vec::new();
+                match reader.read_to_end(&mut data) {
+                    Ok(_) => self.list(&path, ArchiveKind::Zip, Cursor::new(data), entries),
+                    // Kept by the reader, for the zip's own entry
+                    Err(_) => Ok(()),
+                }
+            }
+            Some(kind) => self.list_stream(&path, kind, &mut reader, entries),
+        };
+
+        match reader.finish() {
+            Ok(hashes) => entries[index]
+                .hashes
+                .extend(algorithms.into_iter().zip(hashes)),
+            Err(e) => entries[index].error = Some(e),
+        }
+        // Unless the archive couldn't be read at all
+        if let (Err(e), None) = (listed, &entries[index].error) {
+            entries.push(self.error(&path, e));
+        }
+    }
+
+    fn list_zip<R: Read + Seek>(
+        &self,
+        archive: &Path,
+        reader: R,
+        entries: &mut Vec<DirectoryEntry>,
+    ) -> Result<(), String> {
+        let mut zip = zip::ZipArchive::new(reader).map_err(|e| e.to_string())?;
+
+        for i in 0..zip.len() {
+            // Read without decompressing, so that entries compressed by
+            // unsupported methods are still listed
+            let file = zip.by_index_raw(i).map_err(|e| e.to_string())?;
+            let path = member_path(archive, Path::new(file.name()));
+            let directory = file.is_dir();
+            if path == archive || self.excluded(archive, &path, directory) {
+                continue;
+            }
+
+            let mode = file.unix_mode();
+            let symlink = mode.is_some_and(|m| m & 0o170000 == 0o120000);
+            let time = file.last_modified();
+            let modified =
+                NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)
+                    .and_then(|d| {
+                        d.and_hms_opt(
+                            time.hour() as u32,
+                            time.minute() as u32,
+                            time.second() as u32,
+                        )
+                    });
+            let member = ArchiveMember {
+                compressed_size: Some(file.compressed_size()),
+                modified,
+                mode,
+                ..ArchiveMember::default()
+            };
+            let size = (!directory && !symlink).then_some(file.size());
+            let crc = file.crc32();
+            drop(file);
+
+            let mut entry = self.entry(path, directory, symlink, size, member);
+            // Zip files record the CRC32 of each file's contents
+            if self.algorithms.contains(&HashAlgorithm::Crc32) && size.is_some() {
+                entry
+                    .hashes
+                    .push((HashAlgorithm::Crc32, format!("{:08x}", crc)));
+            }
+
+            // Links keep their target as their contents
+            if symlink {
+                let mut target = String::new();
+                match zip.by_index(i).map(|mut f| f.read_to_string(&mut target)) {
+                    Ok(Ok(_)) => {
+                        if let Some(m) = entry.archived.as_mut() {
+                            m.link_target = Some(PathBuf::from(target));
+                        }
+                    }
+                    Ok(Err(e)) => entry.error = Some(e.to_string()),
+                    Err(e) => entry.error = Some(e.to_string()),
+                }
+            }
+
+            if !self.wants_contents(&entry) {
+                entries.push(entry);
+                continue;
+            }
+            match zip.by_index(i) {
+                Ok(mut f) => self.read_contents(entry, &mut f, entries),
+                Err(e) => {
+                    entry.error = Some(e.to_string());
+                    entries.push(entry);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn list_tar<R: Read>(
+        &self,
+        archive: &Path,
+        reader: R,
+        entries: &mut Vec<DirectoryEntry>,
+    ) -> Result<(), String> {
+        let mut tar = tar::Archive::new(reader);
+
+        for file in tar.entries().map_err(|e| e.to_string())? {
+            let mut file = file.map_err(|e| e.to_string())?;
+            let name = file.path().map_err(|e| e.to_string())?.into_owned();
+            let path = member_path(archive, &name);
+            let header = file.header();
+            let kind = header.entry_type();
+            let directory = kind.is_dir();
+            if path == archive || self.excluded(archive, &path, directory) {
+                continue;
+            }
+
+            let symlink = kind.is_symlink();
+            let id_or_name = |name: Option<&str>, id: std::io::Result<u64>| {
+                name.map(str::to_string)
+                    .or_else(|| id.ok().map(|id| id.to_string()))
+            };
+            let member = ArchiveMember {
+                compressed_size: None,
+                modified: header
+                    .mtime()
+                    .ok()
+                    .and_then(|t| DateTime::from_timestamp(t as i64, 0))
+                    .map(|t| t.with_timezone(&Local).naive_local()),
+                mode: header.mode().ok(),
+                owner: id_or_name(header.username().ok().flatten(), header.uid()),
+                group: id_or_name(header.groupname().ok().flatten(), header.gid()),
+                link_target: file.link_name().ok().flatten().map(|l| l.into_owned()),
+            };
+            let size = (!directory && !symlink).then_some(file.size());
+
+            let entry = self.entry(path, directory, symlink, size, member);
+            match self.wants_contents(&entry) {
+                true => self.read_contents(entry, &mut file, entries),
+                false => entries.push(entry),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::DirectoryColumn;
+    use ignore::overrides::OverrideBuilder;
+    use std::io::Write;
+
+    fn tar(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(contents.len() as u64);
+            if name.ends_with('/') {
+                header.set_entry_type(tar::EntryType::Directory);
+            }
+            builder
+                .append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(contents).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// Lists an archive at `/a/bundle`, as `path|size` lines.
+    fn list(
+        kind: ArchiveKind,
+        data: Vec<u8>,
+        options: &DirectoryOptions,
+        exclude: &str,
+    ) -> Vec<String> {
+        let root = Path::new("/a");
+        let mut overrides = OverrideBuilder::new(root);
+        if !exclude.is_empty() {
+            overrides.add(&format!("!{}", exclude)).unwrap();
+        }
+        let overrides = overrides.build().unwrap();
+
+        let mut entries = Vec::new();
+        ArchiveLister::new(root, options, &overrides)
+            .list(&root.join("bundle"), kind, Cursor::new(data), &mut entries)
+            .unwrap();
+
+        entries
+            .iter()
+            .map(|e| {
+                let hashes: Vec<&str> = e.hashes.iter().map(|(_, h)| h.as_str()).collect();
+                format!(
+                    "{}|{}{}",
+                    e.relative_path.display(),
+                    e.size.map_or("-".to_string(), |s| s.to_string()),
+                    hashes.iter().map(|h| format!("|{}", h)).collect::<String>()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kinds_from_names() {
+        let kind = |name: &str| ArchiveKind::from_path(Path::new(name));
+
+        assert_eq!(kind("a/Bundle.ZIP"), Some(ArchiveKind::Zip));
+        assert_eq!(kind("b.tar"), Some(ArchiveKind::Tar));
+        assert_eq!(kind("b.tar.gz"), Some(ArchiveKind::TarGz));
+        assert_eq!(kind("b.tgz"), Some(ArchiveKind::TarGz));
+        assert_eq!(kind("b.txz"), Some(ArchiveKind::TarXz));
+        assert_eq!(kind("b.gz"), None);
+        assert_eq!(kind("zip"), None);
+    }
+
+    #[test]
+    fn member_paths_stay_in_the_archive() {
+        let archive = Path::new("/a/bundle.zip");
+
+        assert_eq!(
+            member_path(archive, Path::new("../../etc/./passwd")),
+            Path::new("/a/bundle.zip/etc/passwd")
+        );
+        assert_eq!(
+            member_path(archive, Path::new("/docs/a.txt")),
+            Path::new("/a/bundle.zip/docs/a.txt")
+        );
+    }
+
+    #[test]
+    fn lists_tar_entries() {
+        let data = tar(&[
+            ("docs/", ""),
+            ("docs/a.txt", "hello"),
+            ("docs/deep/b.txt", ""),
+        ]);
+        let mut options = DirectoryOptions {
+            directories: true,
+            columns: vec![DirectoryColumn::Path, DirectoryColumn::Crc32],
+            ..DirectoryOptions::default()
+        };
+
+        assert_eq!(
+            list(ArchiveKind::Tar, data.clone(), &options, ""),
+            [
+                "bundle/docs|-",
+                "bundle/docs/a.txt|5|3610a686",
+                "bundle/docs/deep/b.txt|0|00000000"
+            ]
+        );
+
+        // Directories left out leave out what they contain
+        options.columns = vec![DirectoryColumn::Path];
+        assert_eq!(
+            list(ArchiveKind::Tar, data.clone(), &options, "deep"),
+            ["bundle/docs|-", "bundle/docs/a.txt|5"]
+        );
+
+        // The archive is the first level down
+        options.max_depth = Some(3);
+        options.directories = false;
+        assert_eq!(
+            list(ArchiveKind::Tar, data.clone(), &options, ""),
+            ["bundle/docs/a.txt|5"]
+        );
+        assert_eq!(
+            list(ArchiveKind::Tar, data, &options, "*.txt"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn lists_nested_archives() {
+        let inner = tar(&[("c.txt", "abc")]);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&inner).unwrap();
+        let data = zip(&[("a.txt", b"hello"), ("inner.tgz", &gz.finish().unwrap())]);
+
+        let mut options = DirectoryOptions::default();
+        let listed = list(ArchiveKind::Zip, data.clone(), &options, "");
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0], "bundle/a.txt|5");
+
+        options.archive_contents = true;
+        let listed = list(ArchiveKind::Zip, data, &options, "");
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[2], "bundle/inner.tgz/c.txt|3");
+    }
+}
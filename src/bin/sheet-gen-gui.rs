@@ -5,7 +5,18 @@ use std::{env, fs};
 use eframe::egui;
 use egui::Ui;
 use sheet_gen::{
-    builders::{Builder, BuilderTableSource, BuilderWorksheet},
+    aggregate::{GroupBy, Pivot, Summary},
+    builders::{Builder, BuilderJoin, BuilderTableSource, BuilderWorksheet},
+    columns::{ColumnSelection, ComputedColumn},
+    dedupe::{Dedupe, DedupeKeep},
+    dialect::{dialect_char_name, parse_dialect_char, CsvOptions},
+    directory::{DirectoryColumn, DirectoryErrors, DirectoryOptions},
+    feed::{ChannelInfo, DescriptionMode, FeedColumn, FeedOptions},
+    infer::ColumnType,
+    join::{Join, JoinKey, JoinKind},
+    locale::NumberLocale,
+    sort::{SortKey, SortMode},
+    totals::Totals,
     Workbook,
 };
 
@@ -97,49 +108,765 @@ impl SheetgenApp {
             ui.separator();
             ui.horizontal(|ui| {
                 ui.push_id(worksheet_index, |ui| {
-                    egui::ComboBox::from_label("")
-                        .selected_text(match worksheet.table_source.clone().unwrap() {
-                            BuilderTableSource::Csv(_) => "CSV",
-                            BuilderTableSource::Directory(_) => "Directory",
-                            BuilderTableSource::Rss(_) => "RSS",
+                    Self::source_editor(ui, worksheet.table_source.as_mut().unwrap())
+                });
+
+                ui.checkbox(&mut worksheet.headings, "Headings")
+            });
+
+            ui.push_id(worksheet_index, |ui| {
+                Self::source_options(ui, worksheet.table_source.as_mut().unwrap())
+            });
+
+            ui.push_id(("append", worksheet_index), |ui| {
+                ui.collapsing("Append", |ui| Self::append_options(ui, worksheet));
+            });
+
+            ui.push_id(("join", worksheet_index), |ui| {
+                ui.collapsing("Join", |ui| Self::join_options(ui, &mut worksheet.join));
+            });
+
+            ui.push_id(("table", worksheet_index), |ui| {
+                ui.collapsing("Table options", |ui| Self::table_options(ui, worksheet));
+            });
+        });
+
+        ui.add_space(8.0);
+
+        command
+    }
+
+    /// Source type selector and path / URL editor.
+    fn source_editor(ui: &mut Ui, source: &mut BuilderTableSource) {
+        egui::ComboBox::from_label("")
+            .selected_text(match source {
+                BuilderTableSource::Csv(..) => "CSV",
+                BuilderTableSource::Directory(..) => "Directory",
+                BuilderTableSource::Rss(..) => "Feed",
+            })
+            .show_ui(ui, |ui| {
+                let src_string = source.string();
+                let csv_options = match source.clone() {
+                    BuilderTableSource::Csv(_, o) => o,
+                    _ => CsvOptions::default(),
+                };
+                let feed_options = match source.clone() {
+                    BuilderTableSource::Rss(_, o) => o,
+                    _ => FeedOptions::default(),
+                };
+                let directory_options = match source.clone() {
+                    BuilderTableSource::Directory(_, o) => o,
+                    _ => DirectoryOptions::default(),
+                };
+
+                ui.selectable_value(
+                    source,
+                    BuilderTableSource::Csv(src_string.clone(), csv_options),
+                    "CSV",
+                );
+                ui.selectable_value(
+                    source,
+                    BuilderTableSource::Directory(src_string.clone(), directory_options),
+                    "Directory",
+                );
+                ui.selectable_value(
+                    source,
+                    BuilderTableSource::Rss(src_string.clone(), feed_options),
+                    "Feed",
+                );
+            });
+
+        let _ = ui
+            .text_edit_singleline(source.string_mut())
+            .on_hover_text("Source path / URL");
+    }
+
+    fn source_options(ui: &mut Ui, source: &mut BuilderTableSource) {
+        match source {
+            BuilderTableSource::Csv(_, options) => {
+                ui.collapsing("CSV options", |ui| Self::csv_options(ui, options));
+            }
+            BuilderTableSource::Rss(_, options) => {
+                ui.collapsing("Feed options", |ui| Self::feed_options(ui, options));
+            }
+            BuilderTableSource::Directory(_, options) => {
+                ui.collapsing("Directory options", |ui| {
+                    Self::directory_options(ui, options)
+                });
+            }
+        }
+    }
+
+    fn append_options(ui: &mut Ui, worksheet: &mut BuilderWorksheet) {
+        let mut removed = None;
+
+        ui.label("Appended sources:").on_hover_text(
+            "Rows are added after the worksheet source's, lining up columns by heading. \
+             Paths may be glob patterns, e.g. exports/*.csv",
+        );
+        for (i, source) in worksheet.appended.iter_mut().enumerate() {
+            ui.push_id(("appended", i), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("❌").on_hover_text("Remove source").clicked() {
+                        removed = Some(i);
+                    }
+
+                    Self::source_editor(ui, source);
+                });
+                Self::source_options(ui, source);
+            });
+        }
+
+        if let Some(i) = removed {
+            worksheet.appended.remove(i);
+        }
+
+        if ui.button("Add source").clicked() {
+            let source = worksheet.table_source.clone().unwrap();
+            worksheet.appended.push(source);
+        }
+
+        ui.checkbox(&mut worksheet.source_column, "Source column")
+            .on_hover_text("Add a column naming the file or URL each row was read from");
+    }
+
+    fn join_options(ui: &mut Ui, join: &mut Option<BuilderJoin>) {
+        let mut enabled = join.is_some();
+        if ui.checkbox(&mut enabled, "Join another source").changed() {
+            *join = enabled.then(|| BuilderJoin {
+                source: BuilderTableSource::Csv("data.csv".to_string(), CsvOptions::default()),
+                join: Join::default(),
+            });
+        }
+
+        let Some(BuilderJoin { source, join }) = join else {
+            return;
+        };
+
+        ui.horizontal(|ui| Self::source_editor(ui, source));
+        Self::source_options(ui, source);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("kind")
+                .selected_text(join.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in JoinKind::ALL {
+                        ui.selectable_value(&mut join.kind, kind, kind.name());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "inner: matched rows only\n\
+                     left: all of the worksheet's rows\n\
+                     full: all rows of both sources",
+                );
+
+            ui.label("Keys:");
+            Self::parsed_text_edit(
+                ui,
+                "keys",
+                &mut join.keys,
+                |keys| {
+                    keys.iter()
+                        .map(|k| k.name())
+                        .collect::<Vec<String>>()
+                        .join(",")
+                },
+                |text| {
+                    text.split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .filter_map(|s| JoinKey::parse(s).ok())
+                        .collect()
+                },
+                false,
+            )
+            .on_hover_text("COLUMN or LEFT=RIGHT, comma-separated");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Heading suffixes:")
+                .on_hover_text("Added to the headings of columns found in both sources");
+            ui.text_edit_singleline(&mut join.suffixes.0);
+            ui.text_edit_singleline(&mut join.suffixes.1);
+        });
+    }
+
+    /// Editor for a value parsed from text. The text is kept as typed between
+    /// frames (so half-written lines don't vanish) for as long as it still
+    /// parses to the current value.
+    fn parsed_text_edit<T: PartialEq>(
+        ui: &mut Ui,
+        id_source: &str,
+        value: &mut T,
+        to_text: impl Fn(&T) -> String,
+        parse: impl Fn(&str) -> T,
+        multiline: bool,
+    ) -> egui::Response {
+        let id = ui.make_persistent_id(id_source);
+        let mut text = ui
+            .data_mut(|d| d.get_temp::<String>(id))
+            .filter(|t| parse(t) == *value)
+            .unwrap_or_else(|| to_text(value));
+
+        let response = match multiline {
+            true => ui.text_edit_multiline(&mut text),
+            false => ui.text_edit_singleline(&mut text),
+        };
+        if response.changed() {
+            *value = parse(&text);
+        }
+
+        ui.data_mut(|d| d.insert_temp(id, text));
+
+        response
+    }
+
+    fn table_options(ui: &mut Ui, worksheet: &mut BuilderWorksheet) {
+        ui.label("Columns (COLUMN or COLUMN=HEADING per line):")
+            .on_hover_text(
+                "COLUMN is a heading or number; only the listed columns are kept, in order. \
+                 Leave empty to keep all columns",
+            );
+        Self::parsed_text_edit(
+            ui,
+            "columns",
+            &mut worksheet.columns,
+            |columns| {
+                columns
+                    .iter()
+                    .map(|c| c.name())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            },
+            |text| {
+                text.lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .filter_map(|l| ColumnSelection::parse(l).ok())
+                    .collect()
+            },
+            true,
+        );
+
+        Self::computed_options(ui, &mut worksheet.computed);
+        Self::filter_options(ui, &mut worksheet.filters);
+        Self::dedupe_options(ui, &mut worksheet.dedupe);
+        Self::sort_options(ui, &mut worksheet.sort);
+        Self::summary_options(ui, &mut worksheet.summaries);
+        Self::totals_options(ui, &mut worksheet.totals);
+    }
+
+    fn totals_options(ui: &mut Ui, totals: &mut Option<Totals>) {
+        let mut enabled = totals.is_some();
+        if ui.checkbox(&mut enabled, "Totals row").changed() {
+            *totals = enabled.then(Totals::default);
+        }
+
+        let Some(totals) = totals else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Functions:");
+
+            let mut functions = (totals.function, totals.columns.clone());
+            Self::parsed_text_edit(
+                ui,
+                "total functions",
+                &mut functions,
+                |(function, columns)| {
+                    Totals {
+                        function: *function,
+                        columns: columns.clone(),
+                        ..Totals::default()
+                    }
+                    .functions_name()
+                },
+                |text| {
+                    let mut t = Totals::default();
+                    t.set_functions(text).unwrap_or_default();
+                    (t.function, t.columns)
+                },
+                false,
+            )
+            .on_hover_text(
+                "FUNCTION[,COLUMN=FUNCTION...], e.g. sum,Price=avg\n\
+                 Functions: sum, avg, count, min or max",
+            );
+            (totals.function, totals.columns) = functions;
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Subtotals by column:")
+                .on_hover_text("Adds a subtotal row after each run of rows with the same value");
+
+            let mut column = totals.subtotal_by.clone().unwrap_or_default();
+            if ui.text_edit_singleline(&mut column).changed() {
+                totals.subtotal_by = (!column.trim().is_empty()).then_some(column);
+            }
+        });
+
+        ui.checkbox(&mut totals.formulas, "SUBTOTAL formulas")
+            .on_hover_text("Write totals as formulas the spreadsheet recalculates");
+    }
+
+    fn summary_options(ui: &mut Ui, summaries: &mut Vec<Summary>) {
+        let mut removed = None;
+
+        ui.label("Summary sheets:");
+        for (i, summary) in summaries.iter_mut().enumerate() {
+            ui.push_id(("summary", i), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("❌").on_hover_text("Remove summary").clicked() {
+                        removed = Some(i);
+                    }
+
+                    egui::ComboBox::from_id_source("kind")
+                        .selected_text(match summary {
+                            Summary::Group(_) => "Group by",
+                            Summary::Pivot(_) => "Pivot",
                         })
                         .show_ui(ui, |ui| {
-                            let src_string = worksheet.table_source.clone().unwrap().string();
-
-                            ui.selectable_value(
-                                &mut worksheet.table_source,
-                                Some(BuilderTableSource::Csv(src_string.clone())),
-                                "CSV",
-                            );
-                            ui.selectable_value(
-                                &mut worksheet.table_source,
-                                Some(BuilderTableSource::Directory(src_string.clone())),
-                                "Directory",
-                            );
-                            ui.selectable_value(
-                                &mut worksheet.table_source,
-                                Some(BuilderTableSource::Rss(src_string.clone())),
-                                "RSS",
-                            );
+                            if ui
+                                .selectable_label(matches!(summary, Summary::Group(_)), "Group by")
+                                .clicked()
+                            {
+                                *summary = Summary::Group(GroupBy::default());
+                            }
+                            if ui
+                                .selectable_label(matches!(summary, Summary::Pivot(_)), "Pivot")
+                                .clicked()
+                            {
+                                *summary = Summary::Pivot(Pivot::default());
+                            }
                         });
+
+                    match summary {
+                        Summary::Group(g) => Self::parsed_text_edit(
+                            ui,
+                            "group",
+                            g,
+                            |g| g.name(),
+                            |text| GroupBy::parse(text).unwrap_or_default(),
+                            false,
+                        ),
+                        Summary::Pivot(p) => Self::parsed_text_edit(
+                            ui,
+                            "pivot",
+                            p,
+                            |p| p.name(),
+                            |text| Pivot::parse(text).unwrap_or_default(),
+                            false,
+                        ),
+                    }
+                    .on_hover_text(
+                        "Group by: COLUMNS=AGGREGATES, e.g. Type=count,sum:Size (bytes)\n\
+                         Pivot: ROWS,COLUMNS=AGGREGATE, e.g. Region,Year=sum:Sales\n\
+                         Aggregates: count, or count, sum, min, max, avg or distinct:COLUMN",
+                    );
                 });
+            });
+        }
 
-                let _ = ui
-                    .text_edit_singleline(worksheet.table_source.as_mut().unwrap().string_mut())
-                    .on_hover_text("Source path / URL");
+        if let Some(i) = removed {
+            summaries.remove(i);
+        }
 
-                ui.checkbox(&mut worksheet.headings, "Headings")
+        if ui.button("Add summary sheet").clicked() {
+            summaries.push(Summary::Group(GroupBy::default()));
+        }
+    }
+
+    fn dedupe_options(ui: &mut Ui, dedupe: &mut Option<Dedupe>) {
+        let mut enabled = dedupe.is_some();
+        if ui.checkbox(&mut enabled, "Remove duplicate rows").changed() {
+            *dedupe = enabled.then(Dedupe::default);
+        }
+
+        let Some(dedupe) = dedupe else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Key columns:");
+            Self::parsed_text_edit(
+                ui,
+                "dedupe columns",
+                &mut dedupe.columns,
+                |columns| columns.join(","),
+                |text| {
+                    let mut d = Dedupe::default();
+                    d.set_columns(text);
+                    d.columns
+                },
+                false,
+            )
+            .on_hover_text("Comma-separated; leave empty to compare whole rows");
+
+            egui::ComboBox::from_id_source("keep")
+                .selected_text(match dedupe.keep {
+                    DedupeKeep::First => "Keep first",
+                    DedupeKeep::Last => "Keep last",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut dedupe.keep, DedupeKeep::First, "Keep first");
+                    ui.selectable_value(&mut dedupe.keep, DedupeKeep::Last, "Keep last");
+                });
+
+            ui.checkbox(&mut dedupe.count_column, "Count column")
+                .on_hover_text("Add a column counting the duplicates removed for each row");
+        });
+    }
+
+    fn filter_options(ui: &mut Ui, filters: &mut Vec<String>) {
+        ui.label("Filters (one expression per line):")
+            .on_hover_text(
+                "Rows must match every filter, e.g. [Size (bytes)] > 10MB and \
+                 Type startswith \"Video\", or Date >= now() - 7d",
+            );
+        let mut text = filters.join("\n");
+        if ui.text_edit_multiline(&mut text).changed() {
+            *filters = text.split('\n').map(|s| s.to_string()).collect();
+        }
+    }
+
+    fn computed_options(ui: &mut Ui, columns: &mut Vec<ComputedColumn>) {
+        let mut removed = None;
+
+        ui.label("Computed columns:");
+        for (i, column) in columns.iter_mut().enumerate() {
+            ui.push_id(("computed", i), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("❌").on_hover_text("Remove column").clicked() {
+                        removed = Some(i);
+                    }
+
+                    ui.add(
+                        egui::TextEdit::singleline(&mut column.heading)
+                            .desired_width(100.0)
+                            .hint_text("Heading"),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut column.expression)
+                            .desired_width(200.0)
+                            .hint_text("Expression"),
+                    )
+                    .on_hover_text("e.g. round([Size (bytes)] / 1MB, 2)");
+
+                    ui.checkbox(&mut column.formula, "Formula")
+                        .on_hover_text("Write spreadsheet formulas, recalculated when opened");
+                });
+            });
+        }
+
+        if let Some(i) = removed {
+            columns.remove(i);
+        }
+
+        if ui.button("Add computed column").clicked() {
+            columns.push(ComputedColumn {
+                heading: String::new(),
+                expression: String::new(),
+                formula: false,
+            });
+        }
+    }
+
+    fn sort_options(ui: &mut Ui, keys: &mut Vec<SortKey>) {
+        let mut removed = None;
+
+        ui.label("Sort by:");
+        for (i, key) in keys.iter_mut().enumerate() {
+            ui.push_id(("sort", i), |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("❌").on_hover_text("Remove sort key").clicked() {
+                        removed = Some(i);
+                    }
+
+                    ui.add(
+                        egui::TextEdit::singleline(&mut key.column)
+                            .desired_width(120.0)
+                            .hint_text("Column"),
+                    )
+                    .on_hover_text("Heading or number");
+
+                    egui::ComboBox::from_id_source("order")
+                        .selected_text(match key.descending {
+                            true => "Descending",
+                            false => "Ascending",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut key.descending, false, "Ascending");
+                            ui.selectable_value(&mut key.descending, true, "Descending");
+                        });
+
+                    egui::ComboBox::from_id_source("mode")
+                        .selected_text(key.mode.name())
+                        .show_ui(ui, |ui| {
+                            for m in SortMode::ALL {
+                                ui.selectable_value(&mut key.mode, m, m.name());
+                            }
+                        });
+
+                    ui.checkbox(&mut key.empty_first, "Empty first");
+                });
             });
+        }
+
+        if let Some(i) = removed {
+            keys.remove(i);
+        }
+
+        if ui.button("Add sort key").clicked() {
+            keys.push(SortKey::new(""));
+        }
+    }
+
+    /// Editor for a single-character option. The text is parsed once
+    /// editing ends, so that names such as "tab" can be typed out.
+    fn char_option(ui: &mut Ui, value: &mut Option<u8>, label: &str, hint: &str) {
+        let id = ui.make_persistent_id(("char", label));
+        let name = value.map(dialect_char_name).unwrap_or_default();
+        let mut text = match ui.memory(|m| m.has_focus(id)) {
+            true => ui
+                .data_mut(|d| d.get_temp::<String>(id.with("text")))
+                .unwrap_or(name),
+            false => name,
+        };
+
+        ui.label(label);
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut text)
+                .id(id)
+                .desired_width(40.0)
+                .hint_text(hint),
+        );
+        if response.lost_focus() {
+            *value = parse_dialect_char(text.trim());
+        }
+
+        ui.data_mut(|d| d.insert_temp(id.with("text"), text));
+    }
+
+    fn csv_options(ui: &mut Ui, options: &mut CsvOptions) {
+        ui.horizontal(|ui| {
+            Self::char_option(ui, &mut options.delimiter, "Delimiter", "auto");
+
+            let mut quote = Some(options.quote);
+            Self::char_option(ui, &mut quote, "Quote", "\"");
+            options.quote = quote.unwrap_or(b'"');
+
+            Self::char_option(ui, &mut options.escape, "Escape", "none");
+            Self::char_option(ui, &mut options.comment, "Comment", "none");
         });
 
-        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut options.double_quote, "Double quotes")
+                .on_hover_text("Read \"\" in quoted fields as one quote");
+            ui.checkbox(&mut options.trim, "Trim");
+            ui.checkbox(&mut options.flexible, "Flexible rows");
+            ui.label("Skip lines");
+            ui.add(egui::DragValue::new(&mut options.skip_lines));
+        });
 
-        command
+        ui.horizontal(|ui| {
+            let mut encoding = options.encoding.clone().unwrap_or_default();
+
+            ui.label("Encoding");
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut encoding)
+                        .desired_width(120.0)
+                        .hint_text("auto"),
+                )
+                .on_hover_text("e.g. utf-8, utf-16le, windows-1252")
+                .changed()
+            {
+                options.encoding = match encoding.trim().is_empty() {
+                    true => None,
+                    false => Some(encoding),
+                };
+            }
+
+            ui.checkbox(&mut options.infer_types, "Infer types")
+                .on_hover_text("Detect number, date, boolean, percent and currency columns");
+
+            egui::ComboBox::from_label("Number locale")
+                .selected_text(options.locale.name())
+                .show_ui(ui, |ui| {
+                    for name in NumberLocale::PRESETS {
+                        let locale = NumberLocale::from_name(name).unwrap();
+                        let example = locale.example();
+                        ui.selectable_value(
+                            &mut options.locale,
+                            locale,
+                            format!("{} ({})", name, example),
+                        );
+                    }
+                });
+        });
+
+        ui.label("Column types (COLUMN=TYPE per line):")
+            .on_hover_text(
+                "COLUMN is a heading or number; TYPE is one of text, number, integer, boolean, \
+                 date, date:FORMAT, percent or currency",
+            );
+        Self::parsed_text_edit(
+            ui,
+            "column_types",
+            &mut options.column_types,
+            |types| {
+                types
+                    .iter()
+                    .map(|(c, t)| format!("{}={}", c, t.name()))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            },
+            |text| {
+                text.lines()
+                    .filter_map(|l| ColumnType::parse_override(l).ok())
+                    .collect()
+            },
+            true,
+        );
+    }
+
+    fn directory_options(ui: &mut Ui, options: &mut DirectoryOptions) {
+        ui.horizontal_wrapped(|ui| {
+            for column in DirectoryColumn::ALL {
+                let mut enabled = options.columns.contains(&column);
+
+                if ui.checkbox(&mut enabled, column.heading()).changed() {
+                    if enabled {
+                        options.columns.push(column);
+                    } else {
+                        options.columns.retain(|c| *c != column);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut limited = options.max_depth.is_some();
+            if ui.checkbox(&mut limited, "Max depth").changed() {
+                options.max_depth = limited.then_some(1);
+            }
+            if let Some(depth) = &mut options.max_depth {
+                ui.add(egui::DragValue::new(depth).clamp_range(1..=1000))
+                    .on_hover_text("1 lists only the directory's own entries");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.label("Include (one glob per line):")
+                    .on_hover_text("e.g. *.rs; leave empty to list all files");
+                Self::glob_list_edit(ui, &mut options.include);
+            });
+            ui.vertical(|ui| {
+                ui.label("Exclude (one glob per line):")
+                    .on_hover_text("e.g. target");
+                Self::glob_list_edit(ui, &mut options.exclude);
+            });
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut options.ignore_files, "Honour .gitignore");
+            ui.checkbox(&mut options.follow_symlinks, "Follow symlinks");
+            ui.checkbox(&mut options.directories, "List directories")
+                .on_hover_text("With the total size of the files in them");
+            ui.checkbox(&mut options.duplicates, "Duplicates sheet")
+                .on_hover_text("Add a sheet grouping files with identical contents");
+            ui.checkbox(&mut options.summary, "Summary sheet")
+                .on_hover_text(
+                "Add a sheet with the total size, file count and largest file of each directory",
+            );
+            ui.checkbox(&mut options.archive_contents, "List archive contents")
+                .on_hover_text("List the entries of zip and tar archives found, nested ones too");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Type map file:").on_hover_text(
+                "Extra extension categories, one line per category, e.g. Image: png, jpg",
+            );
+
+            let mut path = options.type_map.clone().unwrap_or_default();
+            if ui.text_edit_singleline(&mut path).changed() {
+                options.type_map = (!path.trim().is_empty()).then_some(path);
+            }
+        });
+
+        egui::ComboBox::from_label("Unreadable entries")
+            .selected_text(options.errors.name())
+            .show_ui(ui, |ui| {
+                for e in DirectoryErrors::ALL {
+                    ui.selectable_value(&mut options.errors, e, e.name());
+                }
+            });
+    }
+
+    fn glob_list_edit(ui: &mut Ui, globs: &mut Vec<String>) {
+        let mut text = globs.join("\n");
+        if ui.text_edit_multiline(&mut text).changed() {
+            *globs = text.lines().map(|l| l.to_string()).collect();
+        }
+    }
+
+    fn feed_options(ui: &mut Ui, options: &mut FeedOptions) {
+        ui.horizontal_wrapped(|ui| {
+            for column in FeedColumn::ALL {
+                let mut enabled = options.columns.contains(&column);
+
+                if ui.checkbox(&mut enabled, column.heading()).changed() {
+                    if enabled {
+                        options.columns.push(column);
+                    } else {
+                        options.columns.retain(|c| *c != column);
+                    }
+                }
+            }
+        });
+
+        egui::ComboBox::from_label("Channel info")
+            .selected_text(options.channel_info.name())
+            .show_ui(ui, |ui| {
+                for c in ChannelInfo::ALL {
+                    ui.selectable_value(&mut options.channel_info, c, c.name());
+                }
+            });
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Description")
+                .selected_text(options.description.name())
+                .show_ui(ui, |ui| {
+                    for d in [
+                        DescriptionMode::Raw,
+                        DescriptionMode::Text,
+                        DescriptionMode::Truncate(200),
+                        DescriptionMode::FirstLink,
+                    ] {
+                        ui.selectable_value(&mut options.description, d, d.name());
+                    }
+                });
+
+            if let DescriptionMode::Truncate(n) = &mut options.description {
+                ui.add(egui::DragValue::new(n).clamp_range(1..=100_000))
+                    .on_hover_text("Maximum characters");
+            }
+        });
+
+        ui.label("Merge feeds (one path / URL per line):");
+        let mut additional_feeds = options.additional_feeds.join("\n");
+        if ui.text_edit_multiline(&mut additional_feeds).changed() {
+            options.additional_feeds = additional_feeds
+                .split('\n')
+                .map(|s| s.to_string())
+                .collect();
+        }
     }
 
     pub fn export(&mut self) {
         // Generate worksheets
-        let worksheets = match self.builder.build() {
+        let (worksheets, diagnostics) = match self.builder.build_with_diagnostics() {
             Ok(v) => v,
             Err(e) => {
                 self.export_status = Some(Err(e));
@@ -158,7 +885,12 @@ impl SheetgenApp {
                     return;
                 };
 
-                self.export_status = Some(Ok(format!("Success! Exported to \"{}\".", p)));
+                let mut status = format!("Success! Exported to \"{}\".", p);
+                for d in diagnostics {
+                    status = format!("{}\n{}", status, d);
+                }
+
+                self.export_status = Some(Ok(status));
             }
             None => println!("{}", workbook_xml),
         }
@@ -220,7 +952,10 @@ impl eframe::App for SheetgenApp {
                 if ui.button("+  Add worksheet").clicked() {
                     self.builder.worksheets.push({
                         let mut w = BuilderWorksheet::new();
-                        w.table_source = Some(BuilderTableSource::Csv("data.csv".to_string()));
+                        w.table_source = Some(BuilderTableSource::Csv(
+                            "data.csv".to_string(),
+                            CsvOptions::default(),
+                        ));
                         w.title = format!("Worksheet {}", self.builder.worksheets.len());
                         w
                     })
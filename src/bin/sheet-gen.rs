@@ -1,4 +1,7 @@
-use sheet_gen::{builders::*, *};
+use sheet_gen::{
+    aggregate::*, builders::*, columns::*, dedupe::*, dialect::*, directory::*, encoding::*,
+    feed::*, infer::*, join::*, locale::*, sort::*, totals::*, *,
+};
 use std::{env, fs, process::exit};
 
 const HELP_MESSAGE: &str = include_str!("../include/help_message");
@@ -10,6 +13,36 @@ pub enum CmdOptionType {
     FromDirectory,
     FromRSS,
     OutputPath,
+    Columns,
+    Compute,
+    ComputeFormula,
+    Filter,
+    DedupeBy,
+    Sort,
+    GroupBy,
+    Pivot,
+    Totals,
+    Subtotals,
+    Join,
+    JoinSuffixes,
+    DirColumns,
+    DirMaxDepth,
+    DirInclude,
+    DirExclude,
+    DirErrors,
+    TypeMap,
+    FeedColumns,
+    FeedChannel,
+    FeedDescription,
+    MergeFeed,
+    CsvDelimiter,
+    CsvQuote,
+    CsvEscape,
+    CsvComment,
+    CsvSkipLines,
+    CsvEncoding,
+    CsvColumnType,
+    CsvLocale,
 }
 
 fn die(message: &str, suggest_help: bool) {
@@ -35,6 +68,13 @@ fn die(message: &str, suggest_help: bool) {
     exit(1);
 }
 
+fn note(message: &str) {
+    const BOLD: &str = "\x1B[1m";
+    const RESET: &str = "\x1B[0m";
+
+    eprintln!("{}note{}: {}", BOLD, RESET, message);
+}
+
 fn print_help() {
     println!("{}", HELP_MESSAGE);
     exit(0);
@@ -49,7 +89,14 @@ fn main() {
     // Parse commands
     let mut builder = Builder::new();
     let mut builder_worksheet = BuilderWorksheet::new();
+    let mut feed_options = FeedOptions::default();
+    let mut csv_options = CsvOptions::default();
+    let mut directory_options = DirectoryOptions::default();
     let mut last_option: CmdOptionType = CmdOptionType::None;
+    // Join waiting for the source to join to the last worksheet
+    let mut pending_join: Option<Join> = None;
+    // Whether the next source is appended to the last worksheet
+    let mut pending_append = false;
 
     for (i, arg) in env::args().enumerate() {
         // Skip first command argument (always the executable path)
@@ -67,32 +114,285 @@ fn main() {
             "-r" => last_option = CmdOptionType::FromRSS,
             "-d" => last_option = CmdOptionType::FromDirectory,
             "-o" => last_option = CmdOptionType::OutputPath,
+            "--columns" => last_option = CmdOptionType::Columns,
+            "--compute" => last_option = CmdOptionType::Compute,
+            "--compute-formula" => last_option = CmdOptionType::ComputeFormula,
+            "--filter" => last_option = CmdOptionType::Filter,
+            "--dedupe" => {
+                last_option = CmdOptionType::None;
+                builder_worksheet.dedupe.get_or_insert_with(Dedupe::default);
+            }
+            "--dedupe-by" => last_option = CmdOptionType::DedupeBy,
+            "--keep-last" => {
+                last_option = CmdOptionType::None;
+                builder_worksheet
+                    .dedupe
+                    .get_or_insert_with(Dedupe::default)
+                    .keep = DedupeKeep::Last;
+            }
+            "--duplicate-count" => {
+                last_option = CmdOptionType::None;
+                builder_worksheet
+                    .dedupe
+                    .get_or_insert_with(Dedupe::default)
+                    .count_column = true;
+            }
+            "--sort" => last_option = CmdOptionType::Sort,
+            "--group-by" => last_option = CmdOptionType::GroupBy,
+            "--pivot" => last_option = CmdOptionType::Pivot,
+            "--totals" => last_option = CmdOptionType::Totals,
+            "--subtotals" => last_option = CmdOptionType::Subtotals,
+            "--append" => {
+                if builder.worksheets.is_empty() {
+                    die("--append must follow a source.", true);
+                }
+
+                last_option = CmdOptionType::None;
+                pending_append = true;
+            }
+            "--source-column" => {
+                last_option = CmdOptionType::None;
+                builder_worksheet.source_column = true;
+            }
+            "--join" => last_option = CmdOptionType::Join,
+            "--join-suffixes" => last_option = CmdOptionType::JoinSuffixes,
+            "--total-formulas" => {
+                last_option = CmdOptionType::None;
+                builder_worksheet
+                    .totals
+                    .get_or_insert_with(Totals::default)
+                    .formulas = true;
+            }
+            "--dir-columns" => last_option = CmdOptionType::DirColumns,
+            "--max-depth" => last_option = CmdOptionType::DirMaxDepth,
+            "--include" => last_option = CmdOptionType::DirInclude,
+            "--exclude" => last_option = CmdOptionType::DirExclude,
+            "--dir-errors" => last_option = CmdOptionType::DirErrors,
+            "--type-map" => last_option = CmdOptionType::TypeMap,
+            "--gitignore" => {
+                last_option = CmdOptionType::None;
+                directory_options.ignore_files = true;
+            }
+            "--follow-symlinks" => {
+                last_option = CmdOptionType::None;
+                directory_options.follow_symlinks = true;
+            }
+            "--duplicates" => {
+                last_option = CmdOptionType::None;
+                directory_options.duplicates = true;
+            }
+            "--list-dirs" => {
+                last_option = CmdOptionType::None;
+                directory_options.directories = true;
+            }
+            "--dir-summary" => {
+                last_option = CmdOptionType::None;
+                directory_options.summary = true;
+            }
+            "--archive-contents" => {
+                last_option = CmdOptionType::None;
+                directory_options.archive_contents = true;
+            }
+            "--feed-columns" => last_option = CmdOptionType::FeedColumns,
+            "--feed-channel" => last_option = CmdOptionType::FeedChannel,
+            "--feed-description" => last_option = CmdOptionType::FeedDescription,
+            "--merge-feed" => last_option = CmdOptionType::MergeFeed,
+            "--delimiter" => last_option = CmdOptionType::CsvDelimiter,
+            "--quote" => last_option = CmdOptionType::CsvQuote,
+            "--escape" => last_option = CmdOptionType::CsvEscape,
+            "--comment" => last_option = CmdOptionType::CsvComment,
+            "--skip-lines" => last_option = CmdOptionType::CsvSkipLines,
+            "--encoding" => last_option = CmdOptionType::CsvEncoding,
+            "--column-type" => last_option = CmdOptionType::CsvColumnType,
+            "--locale" => last_option = CmdOptionType::CsvLocale,
+            "--no-infer" => {
+                last_option = CmdOptionType::None;
+                csv_options.infer_types = false;
+            }
+            "--no-double-quote" => {
+                last_option = CmdOptionType::None;
+                csv_options.double_quote = false;
+            }
+            "--trim" => {
+                last_option = CmdOptionType::None;
+                csv_options.trim = true;
+            }
+            "--flexible" => {
+                last_option = CmdOptionType::None;
+                csv_options.flexible = true;
+            }
             arg => {
                 match last_option {
                     CmdOptionType::None => die("invalid syntax.", true),
                     CmdOptionType::WorksheetTitle => builder_worksheet.title = arg.to_string(),
                     CmdOptionType::OutputPath => builder.output = Some(arg.to_string()),
+                    CmdOptionType::Columns => match ColumnSelection::parse_list(arg) {
+                        Ok(columns) => builder_worksheet.columns = columns,
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::Compute | CmdOptionType::ComputeFormula => {
+                        let formula = matches!(last_option, CmdOptionType::ComputeFormula);
+
+                        match ComputedColumn::parse(arg, formula) {
+                            Ok(c) => builder_worksheet.computed.push(c),
+                            Err(e) => die(&format!("{}.", e), true),
+                        }
+                    }
+                    CmdOptionType::Filter => builder_worksheet.filters.push(arg.to_string()),
+                    CmdOptionType::DedupeBy => builder_worksheet
+                        .dedupe
+                        .get_or_insert_with(Dedupe::default)
+                        .set_columns(arg),
+                    CmdOptionType::Sort => match SortKey::parse_list(arg) {
+                        Ok(keys) => builder_worksheet.sort = keys,
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::GroupBy => match GroupBy::parse(arg) {
+                        Ok(g) => builder_worksheet.summaries.push(Summary::Group(g)),
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::Pivot => match Pivot::parse(arg) {
+                        Ok(p) => builder_worksheet.summaries.push(Summary::Pivot(p)),
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::Totals => {
+                        let totals = builder_worksheet.totals.get_or_insert_with(Totals::default);
+
+                        if let Err(e) = totals.set_functions(arg) {
+                            die(&format!("{}.", e), true);
+                        }
+                    }
+                    CmdOptionType::Subtotals => {
+                        builder_worksheet
+                            .totals
+                            .get_or_insert_with(Totals::default)
+                            .subtotal_by = Some(arg.to_string());
+                    }
+                    CmdOptionType::Join => {
+                        if builder.worksheets.is_empty() {
+                            die("--join must follow a source.", true);
+                        }
+
+                        match Join::parse(arg) {
+                            Ok(j) => pending_join = Some(j),
+                            Err(e) => die(&format!("{}.", e), true),
+                        }
+                    }
+                    CmdOptionType::JoinSuffixes => {
+                        let Some(join) = pending_join.as_mut() else {
+                            die("--join-suffixes must follow --join.", true);
+                            unreachable!()
+                        };
+
+                        if let Err(e) = join.set_suffixes(arg) {
+                            die(&format!("{}.", e), true);
+                        }
+                    }
+                    CmdOptionType::DirColumns => match DirectoryColumn::parse_list(arg) {
+                        Ok(columns) => directory_options.columns = columns,
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::DirMaxDepth => match arg.parse::<usize>() {
+                        Ok(n) if n > 0 => directory_options.max_depth = Some(n),
+                        _ => die(&format!("invalid depth \"{}\".", arg), true),
+                    },
+                    CmdOptionType::DirInclude => directory_options.include.push(arg.to_string()),
+                    CmdOptionType::DirExclude => directory_options.exclude.push(arg.to_string()),
+                    CmdOptionType::DirErrors => match DirectoryErrors::from_name(arg) {
+                        Some(e) => directory_options.errors = e,
+                        None => die(&format!("unknown directory error mode \"{}\".", arg), true),
+                    },
+                    CmdOptionType::TypeMap => directory_options.type_map = Some(arg.to_string()),
+                    CmdOptionType::FeedColumns => match FeedColumn::parse_list(arg) {
+                        Ok(columns) => feed_options.columns = columns,
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::FeedChannel => match ChannelInfo::from_name(arg) {
+                        Some(c) => feed_options.channel_info = c,
+                        None => die(&format!("unknown channel info mode \"{}\".", arg), true),
+                    },
+                    CmdOptionType::FeedDescription => match DescriptionMode::from_name(arg) {
+                        Some(d) => feed_options.description = d,
+                        None => die(&format!("unknown description mode \"{}\".", arg), true),
+                    },
+                    CmdOptionType::MergeFeed => feed_options.additional_feeds.push(arg.to_string()),
+                    CmdOptionType::CsvDelimiter
+                    | CmdOptionType::CsvQuote
+                    | CmdOptionType::CsvEscape
+                    | CmdOptionType::CsvComment => {
+                        let Some(c) = parse_dialect_char(arg) else {
+                            die(
+                                &format!("expected a single character, got \"{}\".", arg),
+                                true,
+                            );
+                            unreachable!()
+                        };
+
+                        match last_option {
+                            CmdOptionType::CsvDelimiter => csv_options.delimiter = Some(c),
+                            CmdOptionType::CsvQuote => csv_options.quote = c,
+                            CmdOptionType::CsvEscape => csv_options.escape = Some(c),
+                            CmdOptionType::CsvComment => csv_options.comment = Some(c),
+                            _ => unreachable!(),
+                        }
+                    }
+                    CmdOptionType::CsvSkipLines => match arg.parse::<usize>() {
+                        Ok(n) => csv_options.skip_lines = n,
+                        Err(_) => die(&format!("invalid line count \"{}\".", arg), true),
+                    },
+                    CmdOptionType::CsvEncoding => match encoding_for_label(arg) {
+                        Ok(_) => csv_options.encoding = Some(arg.to_string()),
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::CsvColumnType => match ColumnType::parse_override(arg) {
+                        Ok(t) => csv_options.column_types.push(t),
+                        Err(e) => die(&format!("{}.", e), true),
+                    },
+                    CmdOptionType::CsvLocale => match NumberLocale::from_name(arg) {
+                        Some(l) => csv_options.locale = l,
+                        None => die(&format!("unknown number locale \"{}\".", arg), true),
+                    },
                     _ => {
-                        builder_worksheet.table_source = Some(match last_option {
-                            CmdOptionType::FromCSV => BuilderTableSource::Csv(arg.to_string()),
-                            CmdOptionType::FromDirectory => {
-                                BuilderTableSource::Directory(arg.to_string())
+                        let source = match last_option {
+                            CmdOptionType::FromCSV => {
+                                BuilderTableSource::Csv(arg.to_string(), csv_options.clone())
+                            }
+                            CmdOptionType::FromDirectory => BuilderTableSource::Directory(
+                                arg.to_string(),
+                                directory_options.clone(),
+                            ),
+                            CmdOptionType::FromRSS => {
+                                BuilderTableSource::Rss(arg.to_string(), feed_options.clone())
                             }
-                            CmdOptionType::FromRSS => BuilderTableSource::Rss(arg.to_string()),
                             _ => unreachable!(),
-                        });
+                        };
 
-                        if builder_worksheet.table_source.is_none() {
-                            die("table must have a source.", true);
-                        }
+                        // A source after --join or --append is added to the
+                        // last worksheet rather than starting one
+                        if let Some(join) = pending_join.take() {
+                            if let Some(w) = builder.worksheets.last_mut() {
+                                w.join = Some(BuilderJoin { source, join });
+                            }
+                        } else if pending_append {
+                            if let Some(w) = builder.worksheets.last_mut() {
+                                w.appended.push(source);
+                            }
+                            pending_append = false;
+                        } else {
+                            builder_worksheet.table_source = Some(source);
 
-                        if builder_worksheet.title.is_empty() {
-                            builder_worksheet.title =
-                                format!("Worksheet {}", builder.worksheets.len());
+                            if builder_worksheet.title.is_empty() {
+                                builder_worksheet.title =
+                                    format!("Worksheet {}", builder.worksheets.len());
+                            }
+
+                            builder.worksheets.push(builder_worksheet);
+                            builder_worksheet = BuilderWorksheet::new();
                         }
 
-                        builder.worksheets.push(builder_worksheet);
-                        builder_worksheet = BuilderWorksheet::new();
+                        feed_options = FeedOptions::default();
+                        csv_options = CsvOptions::default();
+                        directory_options = DirectoryOptions::default();
                     }
                 };
 
@@ -110,9 +410,22 @@ fn main() {
         die("no source data given.", true)
     }
 
+    if pending_join.is_some() {
+        die("--join given but no source to join.", true)
+    }
+
+    if pending_append {
+        die("--append given but no source to append.", true)
+    }
+
     // Generate worksheets
-    let worksheets = match builder.build() {
-        Ok(v) => v,
+    let worksheets = match builder.build_with_diagnostics() {
+        Ok((v, diagnostics)) => {
+            for d in diagnostics {
+                note(&d);
+            }
+            v
+        }
         Err(s) => {
             die(s.as_str(), false);
             unreachable!()
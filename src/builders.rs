@@ -1,63 +1,284 @@
+use encoding_rs::Encoding;
 use is_url::is_url;
-use std::fs;
+use std::{fs, io::Read, path::Path};
 
-use crate::{convert::*, Worksheet};
+use crate::{
+    aggregate::Summary,
+    append::append_tables,
+    columns::{compute_columns, project_table, ColumnSelection, ComputedColumn},
+    convert::*,
+    dedupe::Dedupe,
+    dialect::CsvOptions,
+    directory::DirectoryOptions,
+    encoding::{content_type_charset, decode_text, encoding_for_label},
+    expr::filter_table,
+    feed::{is_opml, opml_feed_urls, parse_feed, ChannelInfo, Feed, FeedOptions},
+    join::Join,
+    sort::SortKey,
+    table::Table,
+    totals::{add_totals, Totals},
+    worksheet::sheet_name,
+    Worksheet,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BuilderTableSource {
-    Csv(String),
-    Directory(String),
-    Rss(String),
+    Csv(String, CsvOptions),
+    Directory(String, DirectoryOptions),
+    Rss(String, FeedOptions),
+}
+
+/// Reads the contents of a local file or URL as text, in the given encoding
+/// or one detected from the data (and the HTTP charset, for URLs).
+fn read_source(path: &str, encoding: Option<&'static Encoding>) -> Result<String, String> {
+    if is_url(path) {
+        let response = ureq::get(path).call().map_err(|e| format!("url: {}", e))?;
+        let declared = response
+            .header("Content-Type")
+            .and_then(content_type_charset)
+            .and_then(|c| Encoding::for_label(c.as_bytes()));
+
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("url: {}", e))?;
+
+        Ok(decode_text(&bytes, encoding, declared))
+    } else {
+        let bytes = fs::read(path).map_err(|e| format!("file: {}", e))?;
+
+        Ok(decode_text(&bytes, encoding, None))
+    }
+}
+
+/// Parses a feed document, or each of the feeds listed in an OPML document.
+/// Listed feeds that can't be read are skipped, and their errors added to
+/// `failed`.
+fn read_feeds(content: &str, failed: &mut Vec<String>) -> Result<Vec<Feed>, String> {
+    if !is_opml(content) {
+        return Ok(vec![
+            parse_feed(content).map_err(|e| format!("feed: {}", e))?
+        ]);
+    }
+
+    Ok(opml_feed_urls(content)
+        .iter()
+        .filter_map(|url| {
+            let feed = read_source(url, None)
+                .and_then(|c| parse_feed(&c).map_err(|e| format!("feed: {}", e)));
+            match feed {
+                Ok(feed) => Some(feed),
+                Err(e) => {
+                    failed.push(format!("{}: {}", url, e));
+                    None
+                }
+            }
+        })
+        .collect())
 }
 
 impl BuilderTableSource {
     pub fn resolve(&self) -> Result<String, String> {
         match self {
-            Self::Csv(path) | Self::Rss(path) => {
-                if is_url(path) {
-                    ureq::get(path)
-                        .call()
-                        .map_err(|e| format!("url: {}", e))?
-                        .into_string()
-                        .map_err(|e| format!("url: {}", e))
-                } else {
-                    fs::read_to_string(path).map_err(|e| format!("file: {}", e))
+            Self::Csv(path, options) => {
+                let encoding = match &options.encoding {
+                    Some(label) => {
+                        Some(encoding_for_label(label).map_err(|e| format!("csv: {}", e))?)
+                    }
+                    None => None,
+                };
+
+                read_source(path, encoding)
+            }
+            Self::Rss(path, _) => read_source(path, None),
+            Self::Directory(path, _) => Ok(path.clone()),
+        }
+    }
+
+    /// Reads the source into a table, and any tables the options ask for as
+    /// sheets of their own, e.g. of feed channels, each with a name to follow
+    /// the worksheet title. Problems that don't stop the source being read are
+    /// added to the diagnostics.
+    pub fn to_table(
+        &self,
+        headings: bool,
+        diagnostics: &mut Vec<String>,
+    ) -> Result<(Table, Vec<(String, Table)>), String> {
+        let content = self.resolve();
+
+        Ok(match self {
+            Self::Csv(_, options) => match csv_to_table(&content?, headings, options) {
+                Ok(t) => (t, Vec::new()),
+                Err(e) => return Err(format!("csv: {}", e)),
+            },
+            Self::Directory(_, options) => {
+                let (table, sheets, warnings) = directory_to_table(&content?, headings, options)
+                    .map_err(|e| format!("directory: {}", e))?;
+                diagnostics.extend(warnings);
+                (table, sheets)
+            }
+            Self::Rss(path, options) => {
+                // Feeds that can't be read are skipped, as long as one can be
+                let mut feeds = Vec::new();
+                let mut failed = Vec::new();
+                let additional = options.additional_feeds.iter().map(|p| p.trim());
+                for (path, content) in [(path.as_str(), content)].into_iter().chain(
+                    additional
+                        .filter(|p| !p.is_empty())
+                        .map(|p| (p, read_source(p, None))),
+                ) {
+                    match content.and_then(|c| read_feeds(&c, &mut failed)) {
+                        Ok(f) => feeds.extend(f),
+                        Err(e) => failed.push(format!("{}: {}", path, e)),
+                    }
                 }
+                if feeds.is_empty() && !failed.is_empty() {
+                    return Err(failed.join("; "));
+                }
+                diagnostics.extend(failed.into_iter().map(|e| format!("{}, skipped", e)));
+
+                let channel_table = match options.channel_info {
+                    ChannelInfo::Sheet => {
+                        vec![(
+                            "Channel".to_string(),
+                            feed_channels_to_table(&feeds, headings),
+                        )]
+                    }
+                    _ => Vec::new(),
+                };
+
+                (feeds_to_table(&feeds, headings, options), channel_table)
             }
-            Self::Directory(path) => Ok(path.clone()),
+        })
+    }
+
+    /// Expands a path that is a glob pattern, e.g. `exports/*.csv`, into a
+    /// source per matching path, in order. Other sources, and paths that
+    /// exist as written (e.g. `report[1].csv`), are kept as they are.
+    pub fn expand(&self) -> Result<Vec<BuilderTableSource>, String> {
+        let pattern = self.string();
+        if is_url(&pattern) || !pattern.contains(['*', '?', '[']) || Path::new(&pattern).exists() {
+            return Ok(vec![self.clone()]);
+        }
+
+        let mut sources = Vec::new();
+        for path in glob::glob(&pattern).map_err(|e| format!("glob: {}", e))? {
+            let path = path.map_err(|e| format!("glob: {}", e))?;
+            let mut source = self.clone();
+            source.set_string(path.to_string_lossy().to_string());
+            sources.push(source);
+        }
+
+        if sources.is_empty() {
+            return Err(format!("glob: no paths match \"{}\"", pattern));
         }
+
+        Ok(sources)
     }
 
     pub fn string(&self) -> String {
         match self {
-            Self::Csv(s) => s.clone(),
-            Self::Directory(s) => s.clone(),
-            Self::Rss(s) => s.clone(),
+            Self::Csv(s, _) => s.clone(),
+            Self::Directory(s, _) => s.clone(),
+            Self::Rss(s, _) => s.clone(),
         }
     }
 
     pub fn string_mut(&mut self) -> &mut String {
         match self {
-            Self::Csv(s) => s,
-            Self::Directory(s) => s,
-            Self::Rss(s) => s,
+            Self::Csv(s, _) => s,
+            Self::Directory(s, _) => s,
+            Self::Rss(s, _) => s,
         }
     }
 
     pub fn set_string(&mut self, str_in: String) {
         match self {
-            Self::Csv(s) => *s = str_in,
-            Self::Directory(s) => *s = str_in,
-            Self::Rss(s) => *s = str_in,
+            Self::Csv(s, _) => *s = str_in,
+            Self::Directory(s, _) => *s = str_in,
+            Self::Rss(s, _) => *s = str_in,
         }
     }
 }
 
+/// Reads sources (expanding glob patterns) into one table, one after the
+/// other, and the extra tables of each, named for the source they came from
+/// if there are several.
+fn read_sources(
+    sources: &[BuilderTableSource],
+    headings: bool,
+    source_column: bool,
+    diagnostics: &mut Vec<String>,
+) -> Result<(Table, Vec<(String, Table)>), String> {
+    let mut tables = Vec::new();
+    let mut extra_tables = Vec::new();
+
+    let expanded = sources
+        .iter()
+        .map(|s| s.expand())
+        .collect::<Result<Vec<_>, String>>()?
+        .concat();
+
+    for source in &expanded {
+        let path = source.string();
+        let (table, extra) = source
+            .to_table(headings, diagnostics)
+            .map_err(|e| match expanded.len() {
+                1 => e,
+                _ => format!("{}: {}", path, e),
+            })?;
+
+        let file = Path::new(&path)
+            .file_name()
+            .map_or(path.clone(), |n| n.to_string_lossy().to_string());
+        extra_tables.extend(extra.into_iter().map(|(name, t)| match expanded.len() {
+            1 => (name, t),
+            _ => (format!("{}, {}", name, file), t),
+        }));
+        tables.push((path, table));
+    }
+
+    // A single source is kept as it is, unless it's to be named
+    if tables.len() == 1 && !source_column {
+        return Ok((tables.remove(0).1, extra_tables));
+    }
+
+    Ok((append_tables(tables, source_column), extra_tables))
+}
+
+/// A second source joined to a worksheet's own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuilderJoin {
+    pub source: BuilderTableSource,
+    pub join: Join,
+}
+
 #[derive(Default, Clone)]
 pub struct BuilderWorksheet {
     pub table_source: Option<BuilderTableSource>,
     pub title: String,
     pub headings: bool,
+    /// Sources whose rows are appended to the worksheet source's.
+    pub appended: Vec<BuilderTableSource>,
+    /// Whether a column names the source of each row.
+    pub source_column: bool,
+    /// Source whose rows are joined to the worksheet source's.
+    pub join: Option<BuilderJoin>,
+    /// Columns added to the source's, computed from expressions.
+    pub computed: Vec<ComputedColumn>,
+    /// Columns kept from the source, in order. Empty keeps all of them.
+    pub columns: Vec<ColumnSelection>,
+    /// Expressions every row must match to be kept.
+    pub filters: Vec<String>,
+    /// Removal of duplicate rows, after filtering.
+    pub dedupe: Option<Dedupe>,
+    /// Columns rows are sorted by, earlier keys taking precedence.
+    pub sort: Vec<SortKey>,
+    /// Group-by and pivot summaries, each added as a sheet of its own.
+    pub summaries: Vec<Summary>,
+    /// Totals row (and subtotals) added below the rows.
+    pub totals: Option<Totals>,
 }
 
 impl BuilderWorksheet {
@@ -66,6 +287,16 @@ impl BuilderWorksheet {
             table_source: None,
             title: String::new(),
             headings: true,
+            appended: Vec::new(),
+            source_column: false,
+            join: None,
+            computed: Vec::new(),
+            columns: Vec::new(),
+            filters: Vec::new(),
+            dedupe: None,
+            sort: Vec::new(),
+            summaries: Vec::new(),
+            totals: None,
         }
     }
 }
@@ -85,31 +316,89 @@ impl Builder {
     }
 
     pub fn build(&self) -> Result<Vec<Worksheet>, String> {
+        self.build_with_diagnostics()
+            .map(|(worksheets, _)| worksheets)
+    }
+
+    /// Builds the worksheets, along with notes on what was done to their
+    /// rows, e.g. how many duplicates were removed.
+    pub fn build_with_diagnostics(&self) -> Result<(Vec<Worksheet>, Vec<String>), String> {
         let mut worksheets: Vec<Worksheet> = Vec::new();
+        let mut diagnostics: Vec<String> = Vec::new();
 
         for w in self.worksheets.clone() {
-            let src = w.table_source.unwrap();
+            let mut warnings = Vec::new();
+            let sources = [vec![w.table_source.unwrap()], w.appended].concat();
+            let (table, extra_tables) =
+                read_sources(&sources, w.headings, w.source_column, &mut warnings)?;
 
-            let src_content = match src.resolve() {
-                Ok(s) => s,
-                Err(e) => return Err(e),
+            let table = match &w.join {
+                Some(j) => {
+                    let (other, _) = read_sources(
+                        std::slice::from_ref(&j.source),
+                        w.headings,
+                        false,
+                        &mut warnings,
+                    )
+                    .map_err(|e| format!("join: {}", e))?;
+                    table
+                        .join(other, &j.join)
+                        .map_err(|e| format!("join: {}", e))?
+                }
+                None => table,
             };
 
-            let table = match src {
-                BuilderTableSource::Csv(_) => match csv_to_table(&src_content, w.headings) {
-                    Ok(t) => t,
-                    Err(e) => return Err(format!("csv: {}", e)),
-                },
-                BuilderTableSource::Directory(_) => directory_to_table(&src_content, w.headings),
-                BuilderTableSource::Rss(_) => match rss_to_table(&src_content, w.headings) {
-                    Ok(t) => t,
-                    Err(e) => return Err(format!("rss: {}", e)),
-                },
+            diagnostics.extend(warnings.iter().map(|d| format!("{}: {}", w.title, d)));
+
+            let table =
+                compute_columns(table, &w.computed).map_err(|e| format!("compute: {}", e))?;
+            let mut table =
+                filter_table(table, &w.filters).map_err(|e| format!("filter: {}", e))?;
+            if let Some(d) = &w.dedupe {
+                let removed = table.dedupe(d).map_err(|e| format!("dedupe: {}", e))?;
+                diagnostics.push(format!(
+                    "{}: removed {} duplicate row{}",
+                    w.title,
+                    removed,
+                    if removed == 1 { "" } else { "s" }
+                ));
+            }
+            // Keys yet to be given a column are left out
+            let sort: Vec<SortKey> = w
+                .sort
+                .iter()
+                .filter(|k| !k.column.trim().is_empty())
+                .cloned()
+                .collect();
+            table.sort(&sort).map_err(|e| format!("sort: {}", e))?;
+
+            let summaries = w
+                .summaries
+                .iter()
+                .map(|s| {
+                    let t = s.to_table(&table).map_err(|e| format!("summary: {}", e))?;
+                    Ok((s.sheet_suffix(), t))
+                })
+                .collect::<Result<Vec<(String, Table)>, String>>()?;
+
+            let table = project_table(table, &w.columns).map_err(|e| format!("columns: {}", e))?;
+            let table = match &w.totals {
+                Some(t) => add_totals(table, t).map_err(|e| format!("totals: {}", e))?,
+                None => table,
             };
 
-            worksheets.push(Worksheet::new().with_name(&w.title).with_table(table));
+            let name = sheet_name(&w.title, "", &worksheets);
+            worksheets.push(Worksheet::new().with_name(&name).with_table(table));
+
+            let extra_tables = extra_tables
+                .into_iter()
+                .map(|(name, t)| (format!("({})", name), t));
+            for (suffix, t) in extra_tables.chain(summaries) {
+                let name = sheet_name(&w.title, &suffix, &worksheets);
+                worksheets.push(Worksheet::new().with_name(&name).with_table(t));
+            }
         }
 
-        Ok(worksheets)
+        Ok((worksheets, diagnostics))
     }
 }
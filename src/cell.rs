@@ -1,37 +1,190 @@
+use chrono::{NaiveDateTime, Timelike};
 use std::fmt;
 
 use crate::xml;
 
+/// How a number is displayed, applied through a style derived from the
+/// row's style.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NumberFormat {
+    /// Digits grouped in thousands, with the given number of decimals.
+    Grouped(u8),
+    Percent,
+    /// A currency amount, shown with the given symbol.
+    Currency(String),
+    Date,
+    DateTime,
+}
+
+impl NumberFormat {
+    /// Format string written to the `NumberFormat` element of the style.
+    pub fn format_string(&self) -> String {
+        match self {
+            NumberFormat::Grouped(0) => "#,##0".to_string(),
+            NumberFormat::Grouped(d) => format!("#,##0.{}", "0".repeat(*d as usize)),
+            NumberFormat::Percent => "0.00%".to_string(),
+            NumberFormat::Currency(symbol) => format!(
+                "\"{}\"#,##0.00;\\-\"{}\"#,##0.00",
+                symbol.replace('"', ""),
+                symbol.replace('"', "")
+            ),
+            NumberFormat::Date => "yyyy\\-mm\\-dd".to_string(),
+            NumberFormat::DateTime => "yyyy\\-mm\\-dd\\ hh:mm:ss".to_string(),
+        }
+    }
+
+    /// ID of the style combining `parent_style_id` with this format.
+    pub fn style_id(&self, parent_style_id: &str) -> String {
+        format!(
+            "{}.{}",
+            parent_style_id,
+            match self {
+                NumberFormat::Grouped(d) => format!("Grouped{}", d),
+                NumberFormat::Percent => "Percent".to_string(),
+                NumberFormat::Currency(symbol) => format!(
+                    "Currency{}",
+                    symbol
+                        .chars()
+                        .map(|c| format!("{:x}", c as u32))
+                        .collect::<String>()
+                ),
+                NumberFormat::Date => "Date".to_string(),
+                NumberFormat::DateTime => "DateTime".to_string(),
+            }
+        )
+    }
+
+    pub fn style_xml(&self, parent_style_id: &str) -> String {
+        format!(
+            "<Style ss:ID=\"{}\" ss:Parent=\"{}\">\n<NumberFormat ss:Format=\"{}\"/>\n</Style>",
+            xml::escape_string(&self.style_id(parent_style_id)),
+            xml::escape_string(parent_style_id),
+            xml::escape_string(&self.format_string())
+        )
+    }
+}
+
+/// Part of a formula: R1C1 text, or a reference to a column of the same row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaPart {
+    Text(String),
+    /// 0-based column index, kept separate so the reference can follow the
+    /// column when columns are moved.
+    Column(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    pub parts: Vec<FormulaPart>,
+}
+
+impl Formula {
+    /// The formula in R1C1 notation, starting with `=`.
+    pub fn to_r1c1(&self) -> String {
+        format!(
+            "={}",
+            self.parts
+                .iter()
+                .map(|p| match p {
+                    FormulaPart::Text(s) => s.clone(),
+                    FormulaPart::Column(i) => format!("RC{}", i + 1),
+                })
+                .collect::<String>()
+        )
+    }
+
+    /// Moves the column references, given the new index of each old one.
+    /// Returns `None` if a referenced column has no new index.
+    pub fn remap(&self, new_index: impl Fn(usize) -> Option<usize>) -> Option<Formula> {
+        Some(Formula {
+            parts: self
+                .parts
+                .iter()
+                .map(|p| match p {
+                    FormulaPart::Column(i) => new_index(*i).map(FormulaPart::Column),
+                    p => Some(p.clone()),
+                })
+                .collect::<Option<Vec<FormulaPart>>>()?,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub enum Cell {
     Text(String),
     Number(f64),
+    FormattedNumber(f64, NumberFormat),
+    Boolean(bool),
+    DateTime(NaiveDateTime),
+    /// A formula recalculated by the spreadsheet, with the value it had when
+    /// written.
+    Formula(Formula, Box<Cell>),
     Empty,
 }
 
 impl Cell {
-    pub const fn xml_type_str(&self) -> &'static str {
+    pub fn xml_type_str(&self) -> &'static str {
         match self {
+            Cell::Formula(_, v) => v.xml_type_str(),
             Cell::Text(_) => "String",
-            Cell::Number(_) => "Number",
+            Cell::Number(_) | Cell::FormattedNumber(..) => "Number",
+            Cell::Boolean(_) => "Boolean",
+            Cell::DateTime(_) => "DateTime",
             Cell::Empty => "",
         }
     }
 
+    pub fn number_format(&self) -> Option<NumberFormat> {
+        match self {
+            Cell::FormattedNumber(_, f) => Some(f.clone()),
+            Cell::Formula(_, v) => v.number_format(),
+            Cell::DateTime(d) if d.num_seconds_from_midnight() == 0 => Some(NumberFormat::Date),
+            Cell::DateTime(_) => Some(NumberFormat::DateTime),
+            _ => None,
+        }
+    }
+
+    fn xml_value(&self) -> String {
+        match self {
+            Cell::Boolean(v) => (*v as u8).to_string(),
+            Cell::DateTime(v) => v.format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+            Cell::Formula(_, v) => v.xml_value(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// The `ss:Formula` attribute of formula cells, with a leading space.
+    fn formula_attribute(&self) -> String {
+        match self {
+            Cell::Formula(f, _) => format!(" ss:Formula=\"{}\"", xml::escape_string(&f.to_r1c1())),
+            _ => String::new(),
+        }
+    }
+
     pub fn to_xml(&self) -> String {
-        format!(
-            "<Cell><Data ss:Type=\"{}\">{}</Data></Cell>",
-            self.xml_type_str(),
-            xml::escape_string(self.to_string().as_str())
-        )
+        match self.number_format() {
+            Some(_) => self.to_xml_with_style("Default"),
+            None => format!(
+                "<Cell{}><Data ss:Type=\"{}\">{}</Data></Cell>",
+                self.formula_attribute(),
+                self.xml_type_str(),
+                xml::escape_string(self.xml_value().as_str())
+            ),
+        }
     }
 
     pub fn to_xml_with_style(&self, style_id: &str) -> String {
+        let style_id = match self.number_format() {
+            Some(f) => f.style_id(style_id),
+            None => style_id.to_string(),
+        };
+
         format!(
-            "<Cell ss:StyleID=\"{}\"><Data ss:Type=\"{}\">{}</Data></Cell>",
-            style_id,
+            "<Cell ss:StyleID=\"{}\"{}><Data ss:Type=\"{}\">{}</Data></Cell>",
+            xml::escape_string(&style_id),
+            self.formula_attribute(),
             self.xml_type_str(),
-            xml::escape_string(self.to_string().as_str())
+            xml::escape_string(self.xml_value().as_str())
         )
     }
 }
@@ -43,7 +196,16 @@ impl fmt::Display for Cell {
             "{}",
             match self {
                 Cell::Text(v) => v.to_string(),
-                Cell::Number(v) => v.to_string(),
+                Cell::Number(v) | Cell::FormattedNumber(v, _) => v.to_string(),
+                Cell::Boolean(v) => match v {
+                    true => "TRUE".to_string(),
+                    false => "FALSE".to_string(),
+                },
+                Cell::DateTime(v) => match v.num_seconds_from_midnight() {
+                    0 => v.format("%Y-%m-%d").to_string(),
+                    _ => v.format("%Y-%m-%d %H:%M:%S").to_string(),
+                },
+                Cell::Formula(_, v) => v.to_string(),
                 Cell::Empty => "".to_string(),
             },
         )
@@ -0,0 +1,170 @@
+use crate::{cell::Cell, expr::Expression, row::Row, table::Table};
+
+/// A column kept by a projection, optionally under a new heading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSelection {
+    /// Heading or 1-based index of the column.
+    pub column: String,
+    pub rename: Option<String>,
+}
+
+impl ColumnSelection {
+    /// Parses `COLUMN` or `COLUMN=HEADING`, where the column is a heading or
+    /// 1-based index.
+    pub fn parse(spec: &str) -> Result<ColumnSelection, String> {
+        let (column, rename) = match spec.rsplit_once('=') {
+            Some((c, r)) => (c.trim(), Some(r.trim().to_string())),
+            None => (spec.trim(), None),
+        };
+
+        if column.is_empty() {
+            return Err(format!(
+                "expected COLUMN or COLUMN=HEADING, got \"{}\"",
+                spec
+            ));
+        }
+
+        Ok(ColumnSelection {
+            column: column.to_string(),
+            rename,
+        })
+    }
+
+    /// Parses a comma-separated list of selections.
+    pub fn parse_list(list: &str) -> Result<Vec<ColumnSelection>, String> {
+        list.split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(ColumnSelection::parse)
+            .collect()
+    }
+
+    /// Formats the selection the way [`ColumnSelection::parse`] reads it.
+    pub fn name(&self) -> String {
+        match &self.rename {
+            Some(r) => format!("{}={}", self.column, r),
+            None => self.column.clone(),
+        }
+    }
+}
+
+/// A column added to a table, computed from the other columns of each row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedColumn {
+    pub heading: String,
+    pub expression: String,
+    /// Whether the cells are written as formulas (with their computed values),
+    /// so the spreadsheet recalculates them.
+    pub formula: bool,
+}
+
+impl ComputedColumn {
+    /// Parses `HEADING=EXPRESSION`.
+    pub fn parse(spec: &str, formula: bool) -> Result<ComputedColumn, String> {
+        match spec.split_once('=') {
+            Some((heading, expression)) if !heading.trim().is_empty() => Ok(ComputedColumn {
+                heading: heading.trim().to_string(),
+                expression: expression.trim().to_string(),
+                formula,
+            }),
+            _ => Err(format!("expected HEADING=EXPRESSION, got \"{}\"", spec)),
+        }
+    }
+}
+
+/// Appends computed columns to a table, in order, so that each may refer to
+/// the ones before it.
+pub fn compute_columns(mut table: Table, columns: &[ComputedColumn]) -> Result<Table, String> {
+    for c in columns {
+        let mut expression =
+            Expression::parse(&c.expression).map_err(|e| format!("{}: {}", c.heading, e))?;
+        expression
+            .bind(&table)
+            .map_err(|e| format!("{}: {}", c.heading, e))?;
+
+        let formula = match c.formula {
+            true => Some(
+                expression
+                    .to_formula()
+                    .map_err(|e| format!("{}: {}", c.heading, e))?,
+            ),
+            false => None,
+        };
+
+        let width = table.width();
+        for row in table.rows.iter_mut() {
+            let value = expression.evaluate(row).to_cell();
+
+            row.cells.resize(width, Cell::Empty);
+            row.cells.push(match &formula {
+                Some(f) => Cell::Formula(f.clone(), Box::new(value)),
+                None => value,
+            });
+        }
+
+        if let Some(headings) = table.headings.as_mut() {
+            headings.resize(width, String::new());
+            headings.push(c.heading.clone());
+        }
+    }
+
+    Ok(table)
+}
+
+/// Keeps the selected columns of a table's rows, in the order given, renaming
+/// headings where asked. An empty selection keeps the table as it is.
+pub fn project_table(table: Table, columns: &[ColumnSelection]) -> Result<Table, String> {
+    if columns.is_empty() {
+        return Ok(table);
+    }
+
+    let indices = columns
+        .iter()
+        .map(|s| {
+            table
+                .column_index(&s.column)
+                .ok_or(format!("unknown column \"{}\"", s.column))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+
+    let headings = table.headings.as_ref().map(|headings| {
+        columns
+            .iter()
+            .zip(&indices)
+            .map(|(s, i)| {
+                s.rename
+                    .clone()
+                    .unwrap_or_else(|| headings.get(*i).cloned().unwrap_or_default())
+            })
+            .collect()
+    });
+
+    let new_index = |old: usize| indices.iter().position(|i| *i == old);
+
+    let rows = table
+        .rows
+        .iter()
+        .map(|r| {
+            Row::from_cells(
+                indices
+                    .iter()
+                    .map(|i| match r.cells.get(*i) {
+                        // Formulas follow the columns they refer to, or are
+                        // replaced by their values if those are dropped
+                        Some(Cell::Formula(f, v)) => match f.remap(new_index) {
+                            Some(f) => Cell::Formula(f, v.clone()),
+                            None => v.as_ref().clone(),
+                        },
+                        Some(c) => c.clone(),
+                        None => Cell::Empty,
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+
+    Ok(Table {
+        preamble: table.preamble,
+        headings,
+        rows,
+    })
+}
@@ -1,128 +1,305 @@
-use std::{
-    fs::{self, read_dir},
-    path::{Path, PathBuf},
+use std::path::Path;
+
+use crate::{
+    dialect::CsvOptions,
+    directory::{
+        detect_mime_entries, duplicates_table, hash_entries, read_media_entries, summary_table,
+        walk, AccountNames, DirectoryColumn, DirectoryErrors, DirectoryOptions,
+    },
+    feed::{
+        merge_feed_items, ChannelInfo, DescriptionMode, Feed, FeedColumn, FeedItem, FeedOptions,
+    },
+    filetype::TypeMap,
+    hash::HashAlgorithm,
+    html::{first_link, html_to_text, truncate_text},
+    infer::{infer_column_type, ColumnType},
+    Cell, Row, Table,
 };
 
-use crate::{Cell, Row, Table};
+/// A directory listing, the extra sheets it comes with, and warnings.
+type DirectoryTables = (Table, Vec<(String, Table)>, Vec<String>);
+
+/// Lists a directory as a table, along with the tables of duplicate files and
+/// of directory totals the options ask for, named for their sheets, and
+/// warnings about entries that couldn't be read (unless the options list
+/// those as rows).
+pub fn directory_to_table(
+    path: &str,
+    headings: bool,
+    options: &DirectoryOptions,
+) -> Result<DirectoryTables, String> {
+    let names = match options
+        .columns
+        .iter()
+        .any(|c| matches!(c, DirectoryColumn::Owner | DirectoryColumn::Group))
+    {
+        true => AccountNames::read(),
+        false => AccountNames::default(),
+    };
+
+    let types = match &options.type_map {
+        Some(path) if !path.trim().is_empty() => TypeMap::load(path.trim())?,
+        _ => TypeMap::default(),
+    };
 
-fn recurse(path: impl AsRef<Path>) -> Vec<PathBuf> {
-    let Ok(entries) = read_dir(path) else {
-        return vec![];
+    // The summary counts all the files below the directories it lists, so
+    // the walk goes all the way down, then leaves out what isn't listed
+    let root = Path::new(path);
+    let mut entries = match options.summary {
+        true => walk(
+            root,
+            &DirectoryOptions {
+                directories: true,
+                max_depth: None,
+                ..options.clone()
+            },
+        )?,
+        false => walk(root, options)?,
     };
-    entries
-        .flatten()
-        .flat_map(|entry| {
-            let Ok(meta) = entry.metadata() else {
-                return vec![];
-            };
-            if meta.is_dir() {
-                return recurse(entry.path());
+    let mut sheets = Vec::new();
+    if options.summary {
+        sheets.push((
+            "Summary".to_string(),
+            summary_table(root, &entries, options.max_depth, headings),
+        ));
+        entries.retain(|e| {
+            (options.directories || !e.directory)
+                && options.max_depth.is_none_or(|max| e.depth <= max)
+        });
+    }
+    let mut warnings = Vec::new();
+
+    // Duplicates are found by the first hash column, or else by hashing only
+    // the files the same size as another
+    let algorithms: Vec<HashAlgorithm> = options
+        .columns
+        .iter()
+        .filter_map(|c| c.hash_algorithm())
+        .collect();
+    let duplicates_algorithm = options.duplicates_algorithm();
+    if !algorithms.is_empty() {
+        warnings.extend(hash_entries(&mut entries, &algorithms, false)?);
+    } else if options.duplicates {
+        warnings.extend(hash_entries(&mut entries, &[duplicates_algorithm], true)?);
+    }
+    if options.columns.iter().any(|c| c.is_media()) {
+        warnings.extend(read_media_entries(&mut entries));
+    }
+    let listed = |column| options.columns.contains(&column);
+    if listed(DirectoryColumn::Type)
+        || listed(DirectoryColumn::Mime)
+        || listed(DirectoryColumn::Category)
+    {
+        detect_mime_entries(&mut entries);
+    }
+
+    if options.duplicates {
+        sheets.insert(
+            0,
+            (
+                "Duplicates".to_string(),
+                duplicates_table(&entries, duplicates_algorithm, headings),
+            ),
+        );
+    }
+
+    let mut rows = Vec::new();
+    let error_rows = options.errors == DirectoryErrors::Rows;
+
+    for entry in entries {
+        if let (Some(e), false) = (&entry.error, error_rows) {
+            warnings.push(format!("{}: {}", entry.path.display(), e));
+            continue;
+        }
+
+        let mut cells: Vec<Cell> = options
+            .columns
+            .iter()
+            .map(|c| entry.cell(*c, &names, &types))
+            .collect();
+        if error_rows {
+            cells.push(match &entry.error {
+                Some(e) => Cell::Text(e.clone()),
+                None => Cell::Empty,
+            });
+        }
+
+        rows.push(Row::from_cells(cells));
+    }
+
+    let mut table = Table::new().with_rows(rows);
+
+    if headings {
+        table.headings = Some(
+            options
+                .columns
+                .iter()
+                .map(|c| c.heading().to_string())
+                .chain(error_rows.then(|| "Error".to_string()))
+                .collect(),
+        );
+    }
+
+    Ok((table, sheets, warnings))
+}
+
+fn feed_item_cell(item: &FeedItem, column: FeedColumn, description: DescriptionMode) -> Cell {
+    let value = match column {
+        FeedColumn::Date => item.date.clone(),
+        FeedColumn::Title => item.title.clone(),
+        FeedColumn::Description => item.description.as_ref().map(|d| match description {
+            DescriptionMode::Raw => d.clone(),
+            DescriptionMode::Text | DescriptionMode::FirstLink => html_to_text(d),
+            DescriptionMode::Truncate(n) => truncate_text(&html_to_text(d), n),
+        }),
+        FeedColumn::Link => item.link.clone(),
+        FeedColumn::Author => item.author.clone(),
+        FeedColumn::Categories => match item.categories.is_empty() {
+            true => None,
+            false => Some(item.categories.join(", ")),
+        },
+        FeedColumn::Guid => item.guid.clone(),
+        FeedColumn::Comments => item.comments.clone(),
+        FeedColumn::EnclosureUrl => item.enclosure.as_ref().and_then(|e| e.url.clone()),
+        FeedColumn::EnclosureLength => {
+            let length = item.enclosure.as_ref().and_then(|e| e.length.clone());
+            if let Some(n) = length.as_ref().and_then(|l| l.parse::<f64>().ok()) {
+                return Cell::Number(n);
             }
-            if meta.is_file() {
-                return vec![entry.path()];
+            length
+        }
+        FeedColumn::EnclosureType => item.enclosure.as_ref().and_then(|e| e.mime_type.clone()),
+        FeedColumn::Source => item.source.clone(),
+        FeedColumn::ItunesDuration => item.itunes_duration.clone(),
+        FeedColumn::DcCreator => item.dc_creator.clone(),
+        FeedColumn::Feed => item.feed.clone(),
+    };
+
+    match value {
+        Some(s) => Cell::Text(s),
+        None => Cell::Empty,
+    }
+}
+
+fn feed_channel_rows(feed: &Feed) -> Vec<Row> {
+    let channel = &feed.channel;
+    let text = |value: &Option<String>| match value {
+        Some(s) => Cell::Text(s.clone()),
+        None => Cell::Empty,
+    };
+
+    [
+        ("Title", text(&channel.title)),
+        ("Link", text(&channel.link)),
+        ("Description", text(&channel.description)),
+        ("Last Build Date", text(&channel.last_build_date)),
+        ("Language", text(&channel.language)),
+        (
+            "TTL",
+            match channel.ttl.as_ref().map(|s| s.parse::<f64>()) {
+                Some(Ok(n)) => Cell::Number(n),
+                _ => text(&channel.ttl),
+            },
+        ),
+        ("Image", text(&channel.image)),
+        ("Format", Cell::Text(feed.kind.to_string())),
+    ]
+    .into_iter()
+    .map(|(label, value)| Row::from_cells(vec![Cell::Text(label.to_string()), value]))
+    .collect()
+}
+
+/// Channel metadata rows for each feed, separated by blank rows.
+fn feeds_channel_rows(feeds: &[Feed]) -> Vec<Row> {
+    feeds
+        .iter()
+        .enumerate()
+        .flat_map(|(i, feed)| {
+            let mut rows = feed_channel_rows(feed);
+            if i + 1 < feeds.len() {
+                rows.push(Row::new());
             }
-            vec![]
+            rows
         })
         .collect()
 }
 
-pub fn directory_to_table(path: &str, headings: bool) -> Table {
+/// Builds the item table for one feed, or for several merged feeds (in which
+/// case a "Feed" column is added if not already selected).
+pub fn feeds_to_table(feeds: &[Feed], headings: bool, options: &FeedOptions) -> Table {
+    let link_column = options.description == DescriptionMode::FirstLink;
+
+    let items = match feeds {
+        [feed] => feed.items.clone(),
+        _ => merge_feed_items(feeds),
+    };
+
+    let mut columns = options.columns.clone();
+    if feeds.len() > 1 && !columns.contains(&FeedColumn::Feed) {
+        columns.insert(0, FeedColumn::Feed);
+    }
+
     let mut table = Table::new().with_rows(
-        recurse(path)
+        items
             .iter()
-            .map(|p| {
-                Row::from_cells(vec![
-                    match p.to_str() {
-                        Some(s) => Cell::Text(s.to_string()),
-                        None => Cell::Empty,
-                    },
-                    match p.extension() {
-                        Some(s) => Cell::Text(match s.to_str() {
-                            Some(ext) => format!(
-                                "{} (.{})",
-                                match ext {
-                                    "txt" => "Text".to_string(),
-                                    "png" | "jpg" | "jpeg" | "bmp" | "gif" => "Image".to_string(),
-                                    "mp4" | "mov" | "mkv" => "Video".to_string(),
-                                    "mp3" | "wav" | "aiff" | "ogg" => "Audio".to_string(),
-                                    "md" | "pdf" | "rtf" | "doc" | "docx" | "odt" | "fodt" =>
-                                        "Document".to_string(),
-                                    "ppt" | "pptx" | "fodp" | "odp" => "Slideshow".to_string(),
-                                    "xls" | "xlsx" | "ods" | "fods" => "Spreadsheet".to_string(),
-                                    "c" | "cpp" | "rs" | "js" | "py" | "xml" | "html" | "php"
-                                    | "sh" | "cmd" | "bat" => "Code".to_string(),
-                                    _ => "File".to_string(),
-                                },
-                                ext
-                            ),
-                            None => "File".to_string(),
-                        }),
-                        None => Cell::Text("File".to_string()),
-                    },
-                    match fs::metadata(p) {
-                        Ok(m) => Cell::Number(m.len() as f64),
-                        _ => Cell::Empty,
-                    },
-                ])
+            .map(|item| {
+                Row::from_cells(
+                    columns
+                        .iter()
+                        .flat_map(|c| {
+                            let mut cells = vec![feed_item_cell(item, *c, options.description)];
+
+                            if link_column && *c == FeedColumn::Description {
+                                cells.push(
+                                    match item.description.as_deref().and_then(first_link) {
+                                        Some(s) => Cell::Text(s),
+                                        None => Cell::Empty,
+                                    },
+                                );
+                            }
+
+                            cells
+                        })
+                        .collect(),
+                )
             })
             .collect(),
     );
 
+    if options.channel_info == ChannelInfo::Header {
+        table.preamble = feeds_channel_rows(feeds);
+        table.preamble.push(Row::new());
+    }
+
     if headings {
-        table.headings = Some(vec![
-            "File".to_string(),
-            "Type".to_string(),
-            "Size (bytes)".to_string(),
-        ]);
+        table.headings = Some(
+            columns
+                .iter()
+                .flat_map(|c| match link_column && *c == FeedColumn::Description {
+                    true => vec![c.heading().to_string(), "Description Link".to_string()],
+                    false => vec![c.heading().to_string()],
+                })
+                .collect(),
+        );
     }
 
     table
 }
 
-pub fn rss_to_table(text: &str, headings: bool) -> Result<Table, rss::Error> {
-    let channel = rss::Channel::read_from(text.as_bytes())?;
-    let mut table = Table::new().with_rows(
-        channel
-            .items
-            .iter()
-            .map(|item| {
-                Row::from_cells(vec![
-                    match item.pub_date.clone() {
-                        Some(s) => Cell::Text(s),
-                        None => Cell::Empty,
-                    },
-                    match item.title.clone() {
-                        Some(s) => Cell::Text(s),
-                        None => Cell::Empty,
-                    },
-                    match item.description.clone() {
-                        Some(s) => Cell::Text(s),
-                        None => Cell::Empty,
-                    },
-                    match item.link.clone() {
-                        Some(s) => Cell::Text(s),
-                        None => Cell::Empty,
-                    },
-                ])
-            })
-            .collect(),
-    );
+pub fn feed_channels_to_table(feeds: &[Feed], headings: bool) -> Table {
+    let mut table = Table::new().with_rows(feeds_channel_rows(feeds));
 
     if headings {
-        table.headings = Some(vec![
-            "Date".to_string(),
-            "Title".to_string(),
-            "Description".to_string(),
-            "Link".to_string(),
-        ]);
+        table.headings = Some(vec!["Field".to_string(), "Value".to_string()]);
     }
 
-    Ok(table)
+    table
 }
 
-pub fn csv_to_table(text: &str, headings: bool) -> Result<Table, csv::Error> {
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(headings)
+pub fn csv_to_table(text: &str, headings: bool, options: &CsvOptions) -> Result<Table, csv::Error> {
+    let text = options.skip(text);
+    let mut reader = options
+        .reader_builder(text, headings)
         .from_reader(text.as_bytes());
 
     let mut table = Table::new();
@@ -131,21 +308,35 @@ pub fn csv_to_table(text: &str, headings: bool) -> Result<Table, csv::Error> {
         table.headings = Some(reader.headers()?.iter().map(|s| s.to_string()).collect());
     }
 
-    for record in reader.records() {
-        let record = record?;
+    let records = reader
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+    let column_count = records.iter().map(|r| r.len()).max().unwrap_or(0);
 
+    let column_types: Vec<ColumnType> = (0..column_count)
+        .map(|i| {
+            let heading = table
+                .headings
+                .as_ref()
+                .and_then(|h| h.get(i))
+                .map(|s| s.as_str());
+
+            match options.column_type(i, heading) {
+                Some(t) => t.clone(),
+                None if options.infer_types => {
+                    infer_column_type(records.iter().filter_map(|r| r.get(i)), &options.locale)
+                }
+                None => ColumnType::Text,
+            }
+        })
+        .collect();
+
+    for record in records {
         table.rows.push(Row::from_cells(
             record
                 .iter()
-                .map(|s| {
-                    if let Ok(n) = s.parse::<f64>() {
-                        Cell::Number(n)
-                    } else if let Ok(n) = s.parse::<i64>() {
-                        Cell::Number(n as f64)
-                    } else {
-                        Cell::Text(s.to_string())
-                    }
-                })
+                .zip(column_types.iter())
+                .map(|(s, t)| t.to_cell(s, &options.locale))
                 .collect::<Vec<Cell>>(),
         ));
     }
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use crate::{cell::Cell, table::Table};
+
+/// Heading of the column counting the duplicates removed for each row kept.
+pub const DUPLICATES_HEADING: &str = "Duplicates";
+
+/// Which of a set of duplicate rows is kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupeKeep {
+    First,
+    Last,
+}
+
+/// How duplicate rows are removed from a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dedupe {
+    /// Headings or 1-based indices of the columns rows are compared by. Empty
+    /// compares whole rows.
+    pub columns: Vec<String>,
+    pub keep: DedupeKeep,
+    /// Whether a column counts the duplicates removed for each row kept.
+    pub count_column: bool,
+}
+
+impl Default for Dedupe {
+    fn default() -> Dedupe {
+        Dedupe {
+            columns: Vec::new(),
+            keep: DedupeKeep::First,
+            count_column: false,
+        }
+    }
+}
+
+impl Dedupe {
+    /// Reads comma-separated column names into the key columns.
+    pub fn set_columns(&mut self, list: &str) {
+        self.columns = list
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+    }
+}
+
+/// Text a cell is compared by.
+fn cell_text(cell: Option<&Cell>) -> String {
+    match cell {
+        Some(Cell::Formula(_, v)) => v.to_string(),
+        Some(c) => c.to_string(),
+        None => String::new(),
+    }
+}
+
+impl Table {
+    /// Removes rows whose key columns (or whole rows) repeat those of another,
+    /// keeping the first or last of each, and returns how many were removed.
+    pub fn dedupe(&mut self, dedupe: &Dedupe) -> Result<usize, String> {
+        let indices = match dedupe.columns.is_empty() {
+            true => (0..self.width()).collect(),
+            false => dedupe
+                .columns
+                .iter()
+                .map(|c| {
+                    self.column_index(c)
+                        .ok_or(format!("unknown column \"{}\"", c))
+                })
+                .collect::<Result<Vec<usize>, String>>()?,
+        };
+
+        let keys: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|r| indices.iter().map(|i| cell_text(r.cells.get(*i))).collect())
+            .collect();
+
+        let mut counts: HashMap<&Vec<String>, usize> = HashMap::new();
+        for k in &keys {
+            *counts.entry(k).or_default() += 1;
+        }
+
+        // Rows kept, with the number of other rows sharing their key
+        let mut kept = vec![None; keys.len()];
+        let order: Box<dyn Iterator<Item = usize>> = match dedupe.keep {
+            DedupeKeep::First => Box::new(0..keys.len()),
+            DedupeKeep::Last => Box::new((0..keys.len()).rev()),
+        };
+        for i in order {
+            if let Some(count) = counts.remove(&keys[i]) {
+                kept[i] = Some(count - 1);
+            }
+        }
+
+        let width = self.width();
+        let rows = std::mem::take(&mut self.rows);
+        let removed = rows.len();
+
+        for (mut row, duplicates) in rows.into_iter().zip(kept) {
+            let Some(duplicates) = duplicates else {
+                continue;
+            };
+
+            if dedupe.count_column {
+                row.cells.resize(width, Cell::Empty);
+                row.cells.push(Cell::Number(duplicates as f64));
+            }
+            self.rows.push(row);
+        }
+
+        if dedupe.count_column {
+            if let Some(h) = self.headings.as_mut() {
+                h.resize(width, String::new());
+                h.push(DUPLICATES_HEADING.to_string());
+            }
+        }
+
+        Ok(removed - self.rows.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::Row;
+
+    fn table(rows: &[[&str; 3]]) -> Table {
+        Table::new()
+            .with_headings(vec!["Name", "City", "Age"])
+            .with_rows(
+                rows.iter()
+                    .map(|r| Row::from_cells(r.iter().map(|c| Cell::Text(c.to_string())).collect()))
+                    .collect(),
+            )
+    }
+
+    fn text(table: &Table) -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|r| {
+                r.cells
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+            .collect()
+    }
+
+    const ROWS: [[&str; 3]; 4] = [
+        ["Ann", "Oslo", "30"],
+        ["Bob", "Rome", "41"],
+        ["Ann", "Oslo", "30"],
+        ["Ann", "Bonn", "31"],
+    ];
+
+    #[test]
+    fn whole_rows() {
+        let mut t = table(&ROWS);
+
+        assert_eq!(t.dedupe(&Dedupe::default()), Ok(1));
+        assert_eq!(text(&t), ["Ann|Oslo|30", "Bob|Rome|41", "Ann|Bonn|31"]);
+    }
+
+    #[test]
+    fn key_columns_keeping_last() {
+        let mut dedupe = Dedupe {
+            keep: DedupeKeep::Last,
+            count_column: true,
+            ..Dedupe::default()
+        };
+        dedupe.set_columns(" name , ,");
+        assert_eq!(dedupe.columns, ["name"]);
+
+        let mut t = table(&ROWS);
+        assert_eq!(t.dedupe(&dedupe), Ok(2));
+        assert_eq!(text(&t), ["Bob|Rome|41|0", "Ann|Bonn|31|2"]);
+        assert_eq!(t.headings.unwrap()[3], DUPLICATES_HEADING);
+    }
+
+    #[test]
+    fn unknown_column() {
+        let mut dedupe = Dedupe::default();
+        dedupe.set_columns("Name,Country");
+
+        assert!(table(&ROWS).dedupe(&dedupe).is_err());
+    }
+}
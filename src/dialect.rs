@@ -0,0 +1,227 @@
+use crate::{infer::ColumnType, locale::NumberLocale};
+
+/// How a CSV source is split into records and fields, and how fields are
+/// typed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// Field delimiter, sniffed from the data when not set.
+    pub delimiter: Option<u8>,
+    pub quote: u8,
+    /// Escape character used inside quoted fields, e.g. `\` for `\"`.
+    pub escape: Option<u8>,
+    /// Whether two quotes in a quoted field (`""`) stand for one.
+    pub double_quote: bool,
+    /// Lines starting with this character are ignored.
+    pub comment: Option<u8>,
+    /// Whether whitespace around fields is removed.
+    pub trim: bool,
+    /// Whether records may have differing numbers of fields.
+    pub flexible: bool,
+    /// Number of lines skipped before reading, e.g. a title above the data.
+    pub skip_lines: usize,
+    /// Encoding label (e.g. `windows-1252`), detected from the data when not
+    /// set.
+    pub encoding: Option<String>,
+    /// Whether each column's type is inferred from its values. Otherwise,
+    /// columns without an explicit type are text.
+    pub infer_types: bool,
+    /// Explicit types, keyed by column heading or 1-based index.
+    pub column_types: Vec<(String, ColumnType)>,
+    /// Separators used when reading numbers.
+    pub locale: NumberLocale,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: None,
+            quote: b'"',
+            escape: None,
+            double_quote: true,
+            comment: None,
+            trim: false,
+            flexible: false,
+            skip_lines: 0,
+            encoding: None,
+            infer_types: true,
+            column_types: Vec::new(),
+            locale: NumberLocale::default(),
+        }
+    }
+}
+
+/// Candidate delimiters, least preferred first (ties go to the later ones).
+const DELIMITERS: [u8; 4] = [b'|', b'\t', b';', b','];
+
+/// Parses a single-character option, also accepting the names `tab` and
+/// `space`, and the escape `\t`.
+pub fn parse_dialect_char(value: &str) -> Option<u8> {
+    match value {
+        "tab" | "\\t" | "\t" => Some(b'\t'),
+        "space" => Some(b' '),
+        _ if value.len() == 1 && value.is_ascii() => Some(value.as_bytes()[0]),
+        _ => None,
+    }
+}
+
+/// Formats a single-character option the way [`parse_dialect_char`] reads it.
+pub fn dialect_char_name(c: u8) -> String {
+    match c {
+        b'\t' => "tab".to_string(),
+        b' ' => "space".to_string(),
+        c => (c as char).to_string(),
+    }
+}
+
+/// Counts the fields of a line, ignoring delimiters inside quotes.
+fn count_fields(line: &str, delimiter: u8, quote: u8) -> usize {
+    let mut quoted = false;
+
+    line.bytes().fold(1, |count, b| {
+        if b == quote {
+            quoted = !quoted;
+        }
+        if b == delimiter && !quoted {
+            count + 1
+        } else {
+            count
+        }
+    })
+}
+
+/// Guesses the delimiter of CSV data: the candidate giving the same, largest
+/// number of fields on the most of the first few lines.
+pub fn sniff_delimiter(text: &str, quote: u8, comment: Option<u8>) -> u8 {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter(|l| comment.is_none_or(|c| l.as_bytes()[0] != c))
+        .take(20)
+        .collect();
+
+    DELIMITERS
+        .into_iter()
+        .max_by_key(|d| {
+            let counts: Vec<usize> = lines.iter().map(|l| count_fields(l, *d, quote)).collect();
+            let first = counts.first().copied().unwrap_or(1);
+            let consistent = counts.iter().filter(|c| **c == first).count();
+
+            (first > 1, consistent, first)
+        })
+        .unwrap_or(b',')
+}
+
+impl CsvOptions {
+    /// Returns the text with `skip_lines` lines removed from the start.
+    pub fn skip<'a>(&self, text: &'a str) -> &'a str {
+        let mut rest = text;
+
+        for _ in 0..self.skip_lines {
+            rest = match rest.find('\n') {
+                Some(i) => &rest[i + 1..],
+                None => "",
+            };
+        }
+
+        rest
+    }
+
+    /// Returns the explicit type for the column at `index` (0-based), matched
+    /// by heading first, then by 1-based index.
+    pub fn column_type(&self, index: usize, heading: Option<&str>) -> Option<&ColumnType> {
+        self.column_types
+            .iter()
+            .find(|(c, _)| Some(c.as_str()) == heading)
+            .or_else(|| {
+                self.column_types
+                    .iter()
+                    .find(|(c, _)| c.parse::<usize>().ok() == Some(index + 1))
+            })
+            .map(|(_, t)| t)
+    }
+
+    pub fn reader_builder(&self, text: &str, headings: bool) -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+
+        builder
+            .has_headers(headings)
+            .delimiter(
+                self.delimiter
+                    .unwrap_or_else(|| sniff_delimiter(text, self.quote, self.comment)),
+            )
+            .quote(self.quote)
+            .escape(self.escape)
+            .double_quote(self.double_quote)
+            .comment(self.comment)
+            .flexible(self.flexible)
+            .trim(match self.trim {
+                true => csv::Trim::All,
+                false => csv::Trim::None,
+            });
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_delimiters() {
+        assert_eq!(sniff_delimiter("a,b,c\n1,2,3\n", b'"', None), b',');
+        assert_eq!(sniff_delimiter("a;b\n1,5;2,5\n3;4\n", b'"', None), b';');
+        assert_eq!(sniff_delimiter("a\tb\tc\n1\t2\t3\n", b'"', None), b'\t');
+        assert_eq!(sniff_delimiter("a|b\n1|2\n", b'"', None), b'|');
+        // Delimiters inside quotes and comment lines don't count
+        assert_eq!(
+            sniff_delimiter("# a;b;c;d\n\"x,y\";z\n\"1,2\";3\n", b'"', Some(b'#')),
+            b';'
+        );
+        // A single column, or no data, falls back to commas
+        assert_eq!(sniff_delimiter("one\ntwo\n", b'"', None), b',');
+        assert_eq!(sniff_delimiter("", b'"', None), b',');
+    }
+
+    #[test]
+    fn consistent_counts_beat_more_fields() {
+        let text = "a;b,c,d\ne;f\ng;h,i\n";
+        assert_eq!(sniff_delimiter(text, b'"', None), b';');
+    }
+
+    #[test]
+    fn dialect_chars() {
+        assert_eq!(parse_dialect_char("tab"), Some(b'\t'));
+        assert_eq!(parse_dialect_char("\\t"), Some(b'\t'));
+        assert_eq!(parse_dialect_char("space"), Some(b' '));
+        assert_eq!(parse_dialect_char(";"), Some(b';'));
+        assert_eq!(parse_dialect_char("ab"), None);
+        assert_eq!(parse_dialect_char("€"), None);
+        assert_eq!(dialect_char_name(b'\t'), "tab");
+        assert_eq!(dialect_char_name(b'|'), "|");
+    }
+
+    #[test]
+    fn skips_lines_and_finds_column_types() {
+        let options = CsvOptions {
+            skip_lines: 2,
+            column_types: vec![
+                ("2".to_string(), ColumnType::Integer),
+                ("Amount".to_string(), ColumnType::Currency),
+            ],
+            ..CsvOptions::default()
+        };
+
+        assert_eq!(options.skip("Title\n\na,b\n"), "a,b\n");
+        assert_eq!(options.skip("Title"), "");
+        assert_eq!(
+            options.column_type(1, Some("Amount")),
+            Some(&ColumnType::Currency)
+        );
+        assert_eq!(
+            options.column_type(1, Some("B")),
+            Some(&ColumnType::Integer)
+        );
+        assert_eq!(options.column_type(0, Some("A")), None);
+    }
+}
@@ -0,0 +1,1029 @@
+use chrono::{DateTime, Local};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ignore::{
+    overrides::{Override, OverrideBuilder},
+    WalkBuilder,
+};
+
+use crate::{
+    archive::{ArchiveKind, ArchiveLister, ArchiveMember},
+    cell::{Cell, NumberFormat},
+    filetype::{guess_mime, mime_category, sniff_mime, TypeMap},
+    hash::{hash_files, HashAlgorithm},
+    media::{read_media, MediaInfo},
+    row::Row,
+    table::Table,
+};
+
+/// A column of a directory listing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirectoryColumn {
+    /// Path as found, including the listed directory.
+    Path,
+    Name,
+    /// Directory containing the file.
+    Parent,
+    /// Path from the listed directory.
+    RelativePath,
+    /// Number of directories down from the listed directory, starting at 1.
+    Depth,
+    /// Category and extension, e.g. "Image (.png)", or "Directory", or
+    /// "Link" for links to directories not walked into. The category is as
+    /// in [`DirectoryColumn::Category`].
+    Type,
+    /// MIME type, sniffed from the contents or else guessed from the
+    /// extension.
+    Mime,
+    /// Category of the sniffed MIME type, or else from the extension (see
+    /// [`TypeMap`]), or else of the MIME type guessed from the extension.
+    Category,
+    Size,
+    /// Size of a zip archive entry's compressed data.
+    CompressedSize,
+    Modified,
+    Created,
+    Accessed,
+    /// Unix permissions, e.g. `rw-r--r--`.
+    Permissions,
+    Owner,
+    Group,
+    /// Whether the file, or a directory it is in, is hidden.
+    Hidden,
+    SymlinkTarget,
+    Sha256,
+    Blake3,
+    Crc32,
+    /// Format of an image, audio or video file, e.g. "PNG" or "MP3".
+    Format,
+    /// Width in pixels of an image or video.
+    Width,
+    Height,
+    /// When a photo was taken, from its EXIF data.
+    Taken,
+    /// Make and model of the camera a photo was taken with.
+    Camera,
+    /// Length of audio or video, in seconds.
+    Duration,
+    /// Average bitrate of audio or video, in kilobits per second.
+    Bitrate,
+    /// Title from the tags of audio or video.
+    Title,
+    Artist,
+    Album,
+}
+
+impl DirectoryColumn {
+    pub const ALL: [DirectoryColumn; 31] = [
+        DirectoryColumn::Path,
+        DirectoryColumn::Name,
+        DirectoryColumn::Parent,
+        DirectoryColumn::RelativePath,
+        DirectoryColumn::Depth,
+        DirectoryColumn::Type,
+        DirectoryColumn::Mime,
+        DirectoryColumn::Category,
+        DirectoryColumn::Size,
+        DirectoryColumn::CompressedSize,
+        DirectoryColumn::Modified,
+        DirectoryColumn::Created,
+        DirectoryColumn::Accessed,
+        DirectoryColumn::Permissions,
+        DirectoryColumn::Owner,
+        DirectoryColumn::Group,
+        DirectoryColumn::Hidden,
+        DirectoryColumn::SymlinkTarget,
+        DirectoryColumn::Sha256,
+        DirectoryColumn::Blake3,
+        DirectoryColumn::Crc32,
+        DirectoryColumn::Format,
+        DirectoryColumn::Width,
+        DirectoryColumn::Height,
+        DirectoryColumn::Taken,
+        DirectoryColumn::Camera,
+        DirectoryColumn::Duration,
+        DirectoryColumn::Bitrate,
+        DirectoryColumn::Title,
+        DirectoryColumn::Artist,
+        DirectoryColumn::Album,
+    ];
+
+    /// Name used to select the column on the command line.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            DirectoryColumn::Path => "path",
+            DirectoryColumn::Name => "name",
+            DirectoryColumn::Parent => "parent",
+            DirectoryColumn::RelativePath => "relative-path",
+            DirectoryColumn::Depth => "depth",
+            DirectoryColumn::Type => "type",
+            DirectoryColumn::Mime => "mime",
+            DirectoryColumn::Category => "category",
+            DirectoryColumn::Size => "size",
+            DirectoryColumn::CompressedSize => "compressed-size",
+            DirectoryColumn::Modified => "modified",
+            DirectoryColumn::Created => "created",
+            DirectoryColumn::Accessed => "accessed",
+            DirectoryColumn::Permissions => "permissions",
+            DirectoryColumn::Owner => "owner",
+            DirectoryColumn::Group => "group",
+            DirectoryColumn::Hidden => "hidden",
+            DirectoryColumn::SymlinkTarget => "symlink-target",
+            DirectoryColumn::Sha256 => "sha256",
+            DirectoryColumn::Blake3 => "blake3",
+            DirectoryColumn::Crc32 => "crc32",
+            DirectoryColumn::Format => "format",
+            DirectoryColumn::Width => "width",
+            DirectoryColumn::Height => "height",
+            DirectoryColumn::Taken => "taken",
+            DirectoryColumn::Camera => "camera",
+            DirectoryColumn::Duration => "duration",
+            DirectoryColumn::Bitrate => "bitrate",
+            DirectoryColumn::Title => "title",
+            DirectoryColumn::Artist => "artist",
+            DirectoryColumn::Album => "album",
+        }
+    }
+
+    pub const fn heading(&self) -> &'static str {
+        match self {
+            DirectoryColumn::Path => "File",
+            DirectoryColumn::Name => "Name",
+            DirectoryColumn::Parent => "Parent",
+            DirectoryColumn::RelativePath => "Relative Path",
+            DirectoryColumn::Depth => "Depth",
+            DirectoryColumn::Type => "Type",
+            DirectoryColumn::Mime => "MIME Type",
+            DirectoryColumn::Category => "Category",
+            DirectoryColumn::Size => "Size (bytes)",
+            DirectoryColumn::CompressedSize => "Compressed (bytes)",
+            DirectoryColumn::Modified => "Modified",
+            DirectoryColumn::Created => "Created",
+            DirectoryColumn::Accessed => "Accessed",
+            DirectoryColumn::Permissions => "Permissions",
+            DirectoryColumn::Owner => "Owner",
+            DirectoryColumn::Group => "Group",
+            DirectoryColumn::Hidden => "Hidden",
+            DirectoryColumn::SymlinkTarget => "Symlink Target",
+            DirectoryColumn::Sha256 => "SHA-256",
+            DirectoryColumn::Blake3 => "BLAKE3",
+            DirectoryColumn::Crc32 => "CRC32",
+            DirectoryColumn::Format => "Format",
+            DirectoryColumn::Width => "Width (px)",
+            DirectoryColumn::Height => "Height (px)",
+            DirectoryColumn::Taken => "Date Taken",
+            DirectoryColumn::Camera => "Camera",
+            DirectoryColumn::Duration => "Duration (s)",
+            DirectoryColumn::Bitrate => "Bitrate (kbps)",
+            DirectoryColumn::Title => "Title",
+            DirectoryColumn::Artist => "Artist",
+            DirectoryColumn::Album => "Album",
+        }
+    }
+
+    /// Hash of the file contents the column holds, if any.
+    pub const fn hash_algorithm(&self) -> Option<HashAlgorithm> {
+        match self {
+            DirectoryColumn::Sha256 => Some(HashAlgorithm::Sha256),
+            DirectoryColumn::Blake3 => Some(HashAlgorithm::Blake3),
+            DirectoryColumn::Crc32 => Some(HashAlgorithm::Crc32),
+            _ => None,
+        }
+    }
+
+    /// Whether the column holds properties read from media file headers.
+    pub const fn is_media(&self) -> bool {
+        matches!(
+            self,
+            DirectoryColumn::Format
+                | DirectoryColumn::Width
+                | DirectoryColumn::Height
+                | DirectoryColumn::Taken
+                | DirectoryColumn::Camera
+                | DirectoryColumn::Duration
+                | DirectoryColumn::Bitrate
+                | DirectoryColumn::Title
+                | DirectoryColumn::Artist
+                | DirectoryColumn::Album
+        )
+    }
+
+    pub fn from_name(name: &str) -> Option<DirectoryColumn> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Parses a comma-separated list of column names, e.g. `path,size,owner`.
+    pub fn parse_list(list: &str) -> Result<Vec<DirectoryColumn>, String> {
+        list.split(',')
+            .map(|name| {
+                Self::from_name(name).ok_or(format!("unknown directory column \"{}\"", name.trim()))
+            })
+            .collect()
+    }
+}
+
+/// What is done with entries that can't be read, e.g. for lack of
+/// permission, and symbolic link loops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirectoryErrors {
+    /// Reported in the build diagnostics.
+    Warn,
+    /// Listed as rows, with the error in an "Error" column.
+    Rows,
+}
+
+impl DirectoryErrors {
+    pub const ALL: [DirectoryErrors; 2] = [DirectoryErrors::Warn, DirectoryErrors::Rows];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            DirectoryErrors::Warn => "warn",
+            DirectoryErrors::Rows => "rows",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DirectoryErrors> {
+        Self::ALL
+            .into_iter()
+            .find(|e| e.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryOptions {
+    pub columns: Vec<DirectoryColumn>,
+    /// Deepest level listed, where 1 is the directory's own entries.
+    pub max_depth: Option<usize>,
+    /// Glob patterns of files to list, e.g. `*.rs`. Empty lists all files.
+    pub include: Vec<String>,
+    /// Glob patterns of files and directories to leave out, e.g. `target`.
+    pub exclude: Vec<String>,
+    /// Whether files matched by `.gitignore` and `.ignore` files are left out.
+    pub ignore_files: bool,
+    /// Whether symbolic links to directories are walked into. Loops are
+    /// reported as errors.
+    pub follow_symlinks: bool,
+    /// Whether directories are listed too, with the total size of the files
+    /// in them.
+    pub directories: bool,
+    pub errors: DirectoryErrors,
+    /// Whether a sheet lists the files with identical contents.
+    pub duplicates: bool,
+    /// Whether a sheet sums up each directory, as `du` does.
+    pub summary: bool,
+    /// Path of a type map file adding to the categories of extensions.
+    pub type_map: Option<String>,
+    /// Whether the entries of zip and tar archives found are listed too,
+    /// including those of archives within them.
+    pub archive_contents: bool,
+}
+
+impl Default for DirectoryOptions {
+    fn default() -> DirectoryOptions {
+        DirectoryOptions {
+            columns: vec![
+                DirectoryColumn::Path,
+                DirectoryColumn::Type,
+                DirectoryColumn::Size,
+            ],
+            max_depth: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            ignore_files: false,
+            follow_symlinks: false,
+            directories: false,
+            errors: DirectoryErrors::Warn,
+            duplicates: false,
+            summary: false,
+            type_map: None,
+            archive_contents: false,
+        }
+    }
+}
+
+impl DirectoryOptions {
+    /// Hash duplicates are found by: that of the first hash column, or else
+    /// BLAKE3.
+    pub fn duplicates_algorithm(&self) -> HashAlgorithm {
+        self.columns
+            .iter()
+            .find_map(|c| c.hash_algorithm())
+            .unwrap_or(HashAlgorithm::Blake3)
+    }
+
+    /// Hashes of the contents of files computed for the hash columns, and
+    /// for finding duplicates.
+    pub fn content_hashes(&self) -> Vec<HashAlgorithm> {
+        let mut algorithms: Vec<HashAlgorithm> = self
+            .columns
+            .iter()
+            .filter_map(|c| c.hash_algorithm())
+            .collect();
+        if self.duplicates && algorithms.is_empty() {
+            algorithms.push(self.duplicates_algorithm());
+        }
+        algorithms
+    }
+}
+
+/// A file, directory or symbolic link found in a directory listing, or one
+/// that couldn't be read.
+pub struct DirectoryEntry {
+    pub path: PathBuf,
+    /// Path from the listed directory.
+    pub relative_path: PathBuf,
+    /// Number of directories down from the listed directory, starting at 1.
+    pub depth: usize,
+    /// Metadata of the file, following symbolic links.
+    pub metadata: Option<Metadata>,
+    /// Size of a file, or the total size of the files in a directory.
+    pub size: Option<u64>,
+    pub directory: bool,
+    /// Whether the entry is a symbolic link.
+    pub symlink: bool,
+    /// Why the entry couldn't be read.
+    pub error: Option<String>,
+    /// Hashes of the file contents, where computed.
+    pub hashes: Vec<(HashAlgorithm, String)>,
+    /// Properties of a media file, where read.
+    pub media: Option<MediaInfo>,
+    /// MIME type of a file, where detected.
+    pub mime: Option<String>,
+    /// Whether `mime` was sniffed from the contents, rather than guessed from
+    /// the extension.
+    pub sniffed: bool,
+    /// What an archive records of an entry in it, in place of metadata.
+    pub archived: Option<ArchiveMember>,
+}
+
+impl DirectoryEntry {
+    pub(crate) fn new(root: &Path, path: PathBuf) -> DirectoryEntry {
+        let relative_path = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+
+        DirectoryEntry {
+            depth: relative_path.components().count(),
+            relative_path,
+            metadata: None,
+            size: None,
+            directory: false,
+            symlink: false,
+            error: None,
+            hashes: Vec::new(),
+            media: None,
+            mime: None,
+            sniffed: false,
+            archived: None,
+            path,
+        }
+    }
+}
+
+/// Path an error of a walk is about, if any.
+fn error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        ignore::Error::Loop { child, .. } => Some(child),
+        _ => None,
+    }
+}
+
+/// Message of an error of a walk, without its path.
+fn error_message(error: &ignore::Error) -> String {
+    match error {
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => {
+            error_message(err)
+        }
+        ignore::Error::Loop { ancestor, .. } => {
+            format!("symbolic link loop back to {}", ancestor.display())
+        }
+        e => e.to_string(),
+    }
+}
+
+/// Include and exclude globs of the options, relative to the root.
+fn overrides(root: &Path, options: &DirectoryOptions) -> Result<Override, String> {
+    let globs = |list: &[String]| {
+        list.iter()
+            .map(|g| g.trim().to_string())
+            .filter(|g| !g.is_empty())
+            .collect::<Vec<String>>()
+    };
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in globs(&options.include) {
+        overrides.add(&glob).map_err(|e| e.to_string())?;
+    }
+    for glob in globs(&options.exclude) {
+        overrides
+            .add(&format!("!{}", glob))
+            .map_err(|e| e.to_string())?;
+    }
+
+    overrides.build().map_err(|e| e.to_string())
+}
+
+/// Lists the entries of a directory and its subdirectories, or of a zip or
+/// tar archive, in order of name, including those that couldn't be read.
+/// Fails if the directory or archive itself can't be read.
+pub fn walk(root: &Path, options: &DirectoryOptions) -> Result<Vec<DirectoryEntry>, String> {
+    let overrides = overrides(root, options)?;
+    let archives = ArchiveLister::new(root, options, &overrides);
+    let mut entries = Vec::new();
+
+    if root.is_file() {
+        let kind = ArchiveKind::from_path(root).ok_or(format!(
+            "{}: not a directory or a zip or tar archive",
+            root.display()
+        ))?;
+        archives
+            .list_path(root, kind, &mut entries)
+            .map_err(|e| format!("{}: {}", root.display(), e))?;
+        total_directory_sizes(root, options, &mut entries);
+        return Ok(entries);
+    }
+    // Only what is below the directory can be listed with an error
+    fs::read_dir(root).map_err(|e| format!("{}: {}", root.display(), e))?;
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .overrides(overrides.clone())
+        .follow_links(options.follow_symlinks)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if options.ignore_files {
+        builder
+            .git_ignore(true)
+            .git_exclude(true)
+            .git_global(true)
+            .ignore(true)
+            .parents(true)
+            .require_git(false);
+    }
+    // Directory sizes count the files below the deepest level listed
+    if !options.directories {
+        builder.max_depth(options.max_depth);
+    }
+
+    for result in builder.build() {
+        let (entry, file_type) = match result {
+            Ok(e) if e.depth() == 0 => continue,
+            Ok(e) => (e.path().to_path_buf(), e.file_type()),
+            Err(e) if error_path(&e) == Some(root) => {
+                return Err(format!("{}: {}", root.display(), error_message(&e)));
+            }
+            Err(e) => {
+                let path = error_path(&e).unwrap_or(root).to_path_buf();
+                let mut entry = DirectoryEntry::new(root, path);
+                entry.error = Some(error_message(&e));
+                entries.push(entry);
+                continue;
+            }
+        };
+
+        let directory = file_type.is_some_and(|t| t.is_dir());
+        if directory && !options.directories {
+            continue;
+        }
+
+        let mut entry = DirectoryEntry::new(root, entry);
+        entry.metadata = fs::metadata(&entry.path).ok();
+        entry.size = entry.metadata.as_ref().map(|m| m.len());
+        entry.directory = directory;
+        entry.symlink = fs::symlink_metadata(&entry.path).is_ok_and(|m| m.is_symlink());
+        // Links to directories not walked into have no size of their own
+        if entry.symlink && !directory && entry.metadata.as_ref().is_some_and(|m| m.is_dir()) {
+            entry.size = None;
+        }
+
+        let archive = match options.archive_contents && entry.is_file() {
+            true => ArchiveKind::from_path(&entry.path).map(|k| (entry.path.clone(), k)),
+            false => None,
+        };
+        entries.push(entry);
+        if let Some((path, kind)) = archive {
+            archives.list_file(&path, kind, &mut entries);
+        }
+    }
+
+    total_directory_sizes(root, options, &mut entries);
+    Ok(entries)
+}
+
+/// Sets the sizes of directories listed to the total size of the files in
+/// them, then leaves out entries below the deepest level listed. The files
+/// in an archive count towards the archive's own size instead.
+fn total_directory_sizes(
+    root: &Path,
+    options: &DirectoryOptions,
+    entries: &mut Vec<DirectoryEntry>,
+) {
+    if !options.directories {
+        return;
+    }
+
+    let mut totals: HashMap<PathBuf, u64> = HashMap::new();
+    let archives: HashSet<&Path> = entries
+        .iter()
+        .filter(|e| !e.directory)
+        .map(|e| e.path.as_path())
+        .collect();
+    for e in entries.iter().filter(|e| !e.directory) {
+        for dir in e
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| *a != root && !archives.contains(a))
+        {
+            *totals.entry(dir.to_path_buf()).or_default() += e.size.unwrap_or(0);
+        }
+    }
+
+    for e in entries.iter_mut().filter(|e| e.directory) {
+        e.size = Some(totals.get(&e.path).copied().unwrap_or(0));
+    }
+
+    if let Some(max_depth) = options.max_depth {
+        entries.retain(|e| e.depth <= max_depth);
+    }
+}
+
+/// Category and extension of a file, e.g. "Image (.png)", or just the
+/// category if it has no extension.
+fn file_type_name(path: &Path, category: Option<&str>) -> String {
+    let category = category.unwrap_or("File");
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{} (.{})", category, ext),
+        None => category.to_string(),
+    }
+}
+
+/// User and group names by ID, read from `/etc/passwd` and `/etc/group`.
+#[derive(Default)]
+pub struct AccountNames {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl AccountNames {
+    pub fn read() -> AccountNames {
+        // Lines are NAME:PASSWORD:ID:...
+        let read = |path: &str| {
+            fs::read_to_string(path)
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|l| {
+                    let mut fields = l.split(':');
+                    let name = fields.next()?;
+                    let id = fields.nth(1)?.parse::<u32>().ok()?;
+                    Some((id, name.to_string()))
+                })
+                .collect()
+        };
+
+        AccountNames {
+            users: read("/etc/passwd"),
+            groups: read("/etc/group"),
+        }
+    }
+
+    fn name(names: &HashMap<u32, String>, id: u32) -> Cell {
+        match names.get(&id) {
+            Some(n) => Cell::Text(n.clone()),
+            None => Cell::Number(id as f64),
+        }
+    }
+}
+
+fn time_cell(time: Option<std::io::Result<SystemTime>>) -> Cell {
+    match time {
+        Some(Ok(t)) => Cell::DateTime(DateTime::<Local>::from(t).naive_local()),
+        _ => Cell::Empty,
+    }
+}
+
+/// Permissions of a Unix file mode, e.g. `rw-r--r--`.
+fn mode_text(mode: u32) -> String {
+    (0..9)
+        .map(|i| match mode & (0o400 >> i) {
+            0 => '-',
+            _ => ['r', 'w', 'x'][i % 3],
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn permissions_text(metadata: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    mode_text(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn permissions_text(metadata: &Metadata) -> String {
+    match metadata.permissions().readonly() {
+        true => "r--r--r--".to_string(),
+        false => "rw-rw-rw-".to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn owner_cell(metadata: &Metadata, names: &AccountNames, group: bool) -> Cell {
+    use std::os::unix::fs::MetadataExt;
+
+    match group {
+        true => AccountNames::name(&names.groups, metadata.gid()),
+        false => AccountNames::name(&names.users, metadata.uid()),
+    }
+}
+
+#[cfg(not(unix))]
+fn owner_cell(_: &Metadata, _: &AccountNames, _: bool) -> Cell {
+    Cell::Empty
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(metadata: &Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_: &Metadata) -> bool {
+    false
+}
+
+fn path_cell(path: Option<&Path>) -> Cell {
+    match path.and_then(|p| p.to_str()) {
+        Some(s) => Cell::Text(s.to_string()),
+        None => Cell::Empty,
+    }
+}
+
+impl DirectoryEntry {
+    pub fn cell(&self, column: DirectoryColumn, names: &AccountNames, types: &TypeMap) -> Cell {
+        let metadata = self.metadata.as_ref();
+        if let Some(cell) = self
+            .archived
+            .as_ref()
+            .and_then(|a| self.archived_cell(column, a))
+        {
+            return cell;
+        }
+
+        match column {
+            DirectoryColumn::Path => path_cell(Some(&self.path)),
+            DirectoryColumn::Name => path_cell(self.path.file_name().map(Path::new)),
+            DirectoryColumn::Parent => path_cell(self.path.parent()),
+            DirectoryColumn::RelativePath => path_cell(Some(&self.relative_path)),
+            DirectoryColumn::Depth => Cell::Number(self.depth as f64),
+            DirectoryColumn::Type => match (&self.error, self.directory) {
+                (Some(_), _) => Cell::Empty,
+                (None, true) => Cell::Text("Directory".to_string()),
+                (None, false)
+                    if self.symlink && self.metadata.as_ref().is_some_and(|m| m.is_dir()) =>
+                {
+                    Cell::Text("Link".to_string())
+                }
+                (None, false) => Cell::Text(file_type_name(&self.path, self.category(types))),
+            },
+            DirectoryColumn::Mime => self.mime.clone().map_or(Cell::Empty, Cell::Text),
+            DirectoryColumn::Category => match (&self.error, self.directory) {
+                (Some(_), _) => Cell::Empty,
+                (None, true) => Cell::Text("Directory".to_string()),
+                (None, false) => Cell::Text(self.category(types).unwrap_or("File").to_string()),
+            },
+            DirectoryColumn::Size => match self.size {
+                Some(s) => Cell::Number(s as f64),
+                None => Cell::Empty,
+            },
+            DirectoryColumn::CompressedSize => {
+                match self.archived.as_ref().and_then(|a| a.compressed_size) {
+                    Some(s) => Cell::Number(s as f64),
+                    None => Cell::Empty,
+                }
+            }
+            DirectoryColumn::Modified => time_cell(metadata.map(|m| m.modified())),
+            DirectoryColumn::Created => time_cell(metadata.map(|m| m.created())),
+            DirectoryColumn::Accessed => time_cell(metadata.map(|m| m.accessed())),
+            DirectoryColumn::Permissions => match metadata {
+                Some(m) => Cell::Text(permissions_text(m)),
+                None => Cell::Empty,
+            },
+            DirectoryColumn::Owner | DirectoryColumn::Group => match metadata {
+                Some(m) => owner_cell(m, names, column == DirectoryColumn::Group),
+                None => Cell::Empty,
+            },
+            DirectoryColumn::Hidden if self.error.is_some() => Cell::Empty,
+            DirectoryColumn::Hidden => Cell::Boolean(
+                self.relative_path
+                    .components()
+                    .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                    || metadata.is_some_and(has_hidden_attribute),
+            ),
+            DirectoryColumn::Sha256 | DirectoryColumn::Blake3 | DirectoryColumn::Crc32 => self
+                .hashes
+                .iter()
+                .find(|(a, _)| Some(*a) == column.hash_algorithm())
+                .map_or(Cell::Empty, |(_, h)| Cell::Text(h.clone())),
+            DirectoryColumn::SymlinkTarget => match self.symlink {
+                true => path_cell(fs::read_link(&self.path).ok().as_deref()),
+                false => Cell::Empty,
+            },
+            _ => self.media_cell(column),
+        }
+    }
+
+    /// Cell of a column for an entry of an archive, whose metadata is what
+    /// the archive records.
+    fn archived_cell(&self, column: DirectoryColumn, archived: &ArchiveMember) -> Option<Cell> {
+        let text = |t: &Option<String>| t.clone().map_or(Cell::Empty, Cell::Text);
+
+        Some(match column {
+            DirectoryColumn::Modified => archived.modified.map_or(Cell::Empty, Cell::DateTime),
+            DirectoryColumn::Created | DirectoryColumn::Accessed => Cell::Empty,
+            DirectoryColumn::Permissions => archived
+                .mode
+                .map_or(Cell::Empty, |m| Cell::Text(mode_text(m))),
+            DirectoryColumn::Owner => text(&archived.owner),
+            DirectoryColumn::Group => text(&archived.group),
+            DirectoryColumn::SymlinkTarget => path_cell(archived.link_target.as_deref()),
+            _ => return None,
+        })
+    }
+}
+
+impl DirectoryEntry {
+    fn media_cell(&self, column: DirectoryColumn) -> Cell {
+        let Some(media) = &self.media else {
+            return Cell::Empty;
+        };
+        let text = |t: &Option<String>| t.clone().map_or(Cell::Empty, Cell::Text);
+        let number = |n: Option<f64>, decimals: u8| {
+            n.map_or(Cell::Empty, |n| {
+                Cell::FormattedNumber(n, NumberFormat::Grouped(decimals))
+            })
+        };
+
+        match column {
+            DirectoryColumn::Format => text(&media.format),
+            DirectoryColumn::Width => number(media.width.map(|w| w as f64), 0),
+            DirectoryColumn::Height => number(media.height.map(|h| h as f64), 0),
+            DirectoryColumn::Taken => media.taken.map_or(Cell::Empty, Cell::DateTime),
+            DirectoryColumn::Camera => text(&media.camera),
+            DirectoryColumn::Duration => number(media.duration, 2),
+            DirectoryColumn::Bitrate => number(media.bitrate.map(f64::round), 0),
+            DirectoryColumn::Title => text(&media.title),
+            DirectoryColumn::Artist => text(&media.artist),
+            DirectoryColumn::Album => text(&media.album),
+            _ => Cell::Empty,
+        }
+    }
+
+    /// Whether the entry is a file whose contents can be read.
+    fn is_file(&self) -> bool {
+        self.error.is_none() && self.metadata.as_ref().is_some_and(|m| m.is_file())
+    }
+
+    /// Category of a file: that of its sniffed MIME type, as the contents
+    /// are more telling than the name, or else from its extension, or else
+    /// that of its guessed MIME type.
+    fn category<'a>(&self, types: &'a TypeMap) -> Option<&'a str> {
+        let mime = self.mime.as_deref().and_then(mime_category);
+        match self.sniffed {
+            true => mime.or_else(|| types.category(&self.path)),
+            false => types.category(&self.path).or(mime),
+        }
+    }
+}
+
+/// Hashes the contents of files in parallel, returning warnings about those
+/// that couldn't be read. With `same_size_only`, only files the same size as
+/// another (and not empty) are hashed, as only they can be duplicates. Fails
+/// if the hashes of some files are lost, rather than list the others wrongly.
+pub fn hash_entries(
+    entries: &mut [DirectoryEntry],
+    algorithms: &[HashAlgorithm],
+    same_size_only: bool,
+) -> Result<Vec<String>, String> {
+    let mut sizes: HashMap<u64, usize> = HashMap::new();
+    // Files in archives are hashed as they are read, and can be duplicates
+    // of those on disk
+    for e in entries
+        .iter()
+        .filter(|e| e.is_file() || !e.hashes.is_empty())
+    {
+        *sizes.entry(e.size.unwrap_or(0)).or_default() += 1;
+    }
+
+    let indices: Vec<usize> = (0..entries.len())
+        .filter(|i| {
+            let e = &entries[*i];
+            let size = e.size.unwrap_or(0);
+            e.is_file() && (!same_size_only || (size > 0 && sizes[&size] > 1))
+        })
+        .collect();
+    let paths: Vec<&Path> = indices.iter().map(|i| entries[*i].path.as_path()).collect();
+
+    let mut warnings = Vec::new();
+    for (i, result) in indices.iter().zip(hash_files(&paths, algorithms)?) {
+        match result {
+            Ok(hashes) => entries[*i].hashes = algorithms.iter().copied().zip(hashes).collect(),
+            Err(e) => warnings.push(format!("{}: {}", entries[*i].path.display(), e)),
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Reads the properties of media files from their headers, returning
+/// warnings about those that couldn't be read.
+pub fn read_media_entries(entries: &mut [DirectoryEntry]) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for e in entries.iter_mut().filter(|e| e.is_file()) {
+        match read_media(&e.path) {
+            Ok(media) => e.media = Some(media),
+            Err(err) => warnings.push(format!("{}: {}", e.path.display(), err)),
+        }
+    }
+
+    warnings
+}
+
+/// Detects the MIME types of files, sniffed from the contents of those on
+/// disk, or else guessed from the extension.
+pub fn detect_mime_entries(entries: &mut [DirectoryEntry]) {
+    for e in entries.iter_mut() {
+        let sniffed = e.is_file().then(|| sniff_mime(&e.path)).flatten();
+        e.sniffed = sniffed.is_some();
+        e.mime = match (e.is_file(), &e.archived) {
+            (true, _) => sniffed.or_else(|| guess_mime(&e.path)),
+            (false, Some(_)) if !e.directory && !e.symlink => guess_mime(&e.path),
+            _ => None,
+        };
+    }
+}
+
+/// Totals of the files in a directory and its subdirectories.
+#[derive(Default)]
+struct DirectorySummary<'a> {
+    size: u64,
+    files: usize,
+    largest: Option<&'a DirectoryEntry>,
+}
+
+/// Sums up the listed directory and each directory in it, in the order
+/// listed: the total size and number of the files in it and its
+/// subdirectories, and the largest of them. Names are indented by depth.
+/// Directories below `max_depth` are left out, but their files still count.
+/// The files in an archive count towards the archive's directories only.
+pub fn summary_table(
+    root: &Path,
+    entries: &[DirectoryEntry],
+    max_depth: Option<usize>,
+    headings: bool,
+) -> Table {
+    let archives: HashSet<&Path> = entries
+        .iter()
+        .filter(|e| !e.directory)
+        .map(|e| e.path.as_path())
+        .collect();
+
+    let mut summaries: HashMap<&Path, DirectorySummary> = HashMap::new();
+    for e in entries.iter().filter(|e| !e.directory && e.error.is_none()) {
+        let Some(size) = e.size else {
+            continue;
+        };
+
+        for dir in e
+            .path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| a.starts_with(root) && !archives.contains(a))
+        {
+            let summary = summaries.entry(dir).or_default();
+            summary.size += size;
+            summary.files += 1;
+            if summary.largest.and_then(|l| l.size) < Some(size) {
+                summary.largest = Some(e);
+            }
+        }
+    }
+
+    let directories = entries
+        .iter()
+        .filter(|e| e.directory && e.error.is_none())
+        .filter(|e| max_depth.is_none_or(|max| e.depth <= max));
+
+    let mut rows = vec![summary_row(root, root, 0, summaries.get(root))];
+    for e in directories {
+        rows.push(summary_row(
+            root,
+            &e.path,
+            e.depth,
+            summaries.get(e.path.as_path()),
+        ));
+    }
+
+    let mut table = Table::new().with_rows(rows);
+    if headings {
+        table = table.with_headings(vec![
+            "Directory",
+            "Relative Path",
+            "Depth",
+            "Size (bytes)",
+            "Files",
+            "Largest File",
+            "Largest Size (bytes)",
+        ]);
+    }
+
+    table
+}
+
+fn summary_row(root: &Path, path: &Path, depth: usize, summary: Option<&DirectorySummary>) -> Row {
+    let name = match depth {
+        0 => path.to_string_lossy(),
+        _ => path.file_name().unwrap_or_default().to_string_lossy(),
+    };
+    let relative = |p: &Path| p.strip_prefix(root).unwrap_or(p).to_path_buf();
+    let largest = summary.and_then(|s| s.largest);
+
+    Row::from_cells(vec![
+        Cell::Text(format!("{}{}", "  ".repeat(depth), name)),
+        path_cell(Some(&relative(path))),
+        Cell::Number(depth as f64),
+        Cell::Number(summary.map_or(0, |s| s.size) as f64),
+        Cell::Number(summary.map_or(0, |s| s.files) as f64),
+        largest.map_or(Cell::Empty, |l| path_cell(Some(&relative(&l.path)))),
+        largest
+            .and_then(|l| l.size)
+            .map_or(Cell::Empty, |s| Cell::Number(s as f64)),
+    ])
+}
+
+/// Lists the files with identical contents (by the given hash), a group per
+/// content, largest waste first. Every copy but the first counts as wasted.
+/// Empty files are left out.
+pub fn duplicates_table(
+    entries: &[DirectoryEntry],
+    algorithm: HashAlgorithm,
+    headings: bool,
+) -> Table {
+    let mut groups: HashMap<(u64, &str), Vec<&DirectoryEntry>> = HashMap::new();
+    for e in entries.iter().filter(|e| e.size > Some(0)) {
+        if let Some((_, hash)) = e.hashes.iter().find(|(a, _)| *a == algorithm) {
+            groups
+                .entry((e.size.unwrap_or(0), hash))
+                .or_default()
+                .push(e);
+        }
+    }
+
+    let mut groups: Vec<((u64, &str), Vec<&DirectoryEntry>)> =
+        groups.into_iter().filter(|(_, g)| g.len() > 1).collect();
+    groups.sort_by(|((a_size, a_hash), a), ((b_size, b_hash), b)| {
+        let waste = |size: &u64, files: &Vec<&DirectoryEntry>| size * (files.len() as u64 - 1);
+        waste(b_size, b)
+            .cmp(&waste(a_size, a))
+            .then(a_hash.cmp(b_hash))
+    });
+
+    let mut rows = Vec::new();
+    for (group, ((size, hash), files)) in groups.iter().enumerate() {
+        for (copy, e) in files.iter().enumerate() {
+            rows.push(Row::from_cells(vec![
+                Cell::Number((group + 1) as f64),
+                Cell::Text(hash.to_string()),
+                path_cell(Some(&e.path)),
+                Cell::Number(*size as f64),
+                Cell::Number(if copy == 0 { 0.0 } else { *size as f64 }),
+            ]));
+        }
+    }
+
+    let mut table = Table::new().with_rows(rows);
+    if headings {
+        table = table.with_headings(vec![
+            "Group",
+            algorithm.heading(),
+            "File",
+            "Size (bytes)",
+            "Wasted (bytes)",
+        ]);
+    }
+
+    table
+}
@@ -0,0 +1,148 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// Looks up an encoding by any of its WHATWG labels, e.g. `utf-16le`,
+/// `latin1` or `windows-1252`.
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or(format!("unknown encoding \"{}\"", label))
+}
+
+/// Returns the `charset` parameter of a `Content-Type` header value.
+pub fn content_type_charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        match name.trim().eq_ignore_ascii_case("charset") {
+            true => Some(value.trim().trim_matches('"')),
+            false => None,
+        }
+    })
+}
+
+/// Returns the encoding named in an `<?xml ... encoding="..."?>` declaration.
+fn xml_declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let prolog = bytes.strip_prefix(b"<?xml")?;
+    let end = prolog.iter().position(|b| *b == b'>')?;
+    let prolog = std::str::from_utf8(&prolog[..end]).ok()?;
+    let value = prolog
+        .split_once("encoding")?
+        .1
+        .trim_start()
+        .strip_prefix('=')?;
+    let value = value.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+
+    Encoding::for_label(value[1..].split(quote).next()?.as_bytes())
+}
+
+/// Spots UTF-16 without a byte order mark by the zero bytes that ASCII
+/// characters leave in every other position.
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(1024) & !1];
+    if sample.is_empty() {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let zeros_at = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+
+    if zeros_at(1) * 10 >= pairs * 7 && zeros_at(0) == 0 {
+        Some(UTF_16LE)
+    } else if zeros_at(0) * 10 >= pairs * 7 && zeros_at(1) == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Picks the encoding of some text: a byte order mark wins, then the declared
+/// encoding (e.g. an HTTP charset), then an XML declaration, then UTF-16 or
+/// UTF-8 if the bytes look like it, falling back to Windows-1252 (a superset
+/// of Latin-1).
+pub fn detect_encoding(bytes: &[u8], declared: Option<&'static Encoding>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    if let Some(encoding) = declared.or_else(|| xml_declared_encoding(bytes)) {
+        return encoding;
+    }
+
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return encoding;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+/// Decodes text in the given encoding, or a detected one when `None`. Any
+/// byte order mark is removed, and malformed sequences become U+FFFD.
+pub fn decode_text(
+    bytes: &[u8],
+    encoding: Option<&'static Encoding>,
+    declared: Option<&'static Encoding>,
+) -> String {
+    let encoding = encoding.unwrap_or_else(|| detect_encoding(bytes, declared));
+
+    encoding.decode_with_bom_removal(bytes).0.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn detection_order() {
+        let latin = b"caf\xe9";
+        assert_eq!(detect_encoding(b"cafe", None), UTF_8);
+        assert_eq!(detect_encoding(latin, None), WINDOWS_1252);
+        assert_eq!(detect_encoding(&utf16le("a,b\n1,2"), None), UTF_16LE);
+        assert_eq!(detect_encoding(b"\0a\0b", None), UTF_16BE);
+
+        // A byte order mark beats a declared encoding
+        assert_eq!(
+            detect_encoding(b"\xef\xbb\xbfcaf\xc3\xa9", Some(WINDOWS_1252)),
+            UTF_8
+        );
+        // A declared encoding beats the XML declaration
+        let xml = b"<?xml version=\"1.0\" encoding='ISO-8859-1'?><a/>";
+        assert_eq!(detect_encoding(xml, Some(UTF_8)), UTF_8);
+        assert_eq!(detect_encoding(xml, None), WINDOWS_1252);
+        // And the XML declaration beats what the bytes look like
+        assert_eq!(
+            detect_encoding(b"<?xml encoding=\"utf-8\"?>\xe9", None),
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn decodes_text() {
+        assert_eq!(decode_text(b"caf\xe9", None, None), "café");
+        assert_eq!(decode_text(b"\xef\xbb\xbfhi", None, None), "hi");
+        assert_eq!(decode_text(&utf16le("hé"), None, None), "hé");
+        assert_eq!(decode_text(b"caf\xe9", Some(UTF_8), None), "caf\u{fffd}");
+    }
+
+    #[test]
+    fn labels_and_charsets() {
+        assert_eq!(encoding_for_label(" Latin1 "), Ok(WINDOWS_1252));
+        assert!(encoding_for_label("klingon").is_err());
+        assert_eq!(
+            content_type_charset("text/csv; header=present; Charset=\"utf-16le\""),
+            Some("utf-16le")
+        );
+        assert_eq!(content_type_charset("text/csv"), None);
+    }
+}
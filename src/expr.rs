@@ -0,0 +1,1154 @@
+use chrono::{Datelike, Duration, Local, NaiveDateTime};
+use regex::Regex;
+use std::{cmp::Ordering, fmt};
+
+use crate::{
+    cell::{Cell, Formula, FormulaPart},
+    infer::{parse_date, parse_number},
+    row::Row,
+    table::Table,
+};
+
+/// Size units accepted after a number, e.g. `10MB`, and their factors.
+const SIZE_UNITS: [(&str, f64); 9] = [
+    ("b", 1.0),
+    ("kb", 1e3),
+    ("mb", 1e6),
+    ("gb", 1e9),
+    ("tb", 1e12),
+    ("kib", 1024.0),
+    ("mib", 1048576.0),
+    ("gib", 1073741824.0),
+    ("tib", 1099511627776.0),
+];
+
+/// Duration units accepted after a number, e.g. `7d`, in seconds.
+const DURATION_UNITS: [(&str, f64); 5] = [
+    ("s", 1.0),
+    ("min", 60.0),
+    ("h", 3600.0),
+    ("d", 86400.0),
+    ("w", 604800.0),
+];
+
+/// Operator symbols, longest first so that `<=` isn't read as `<`.
+const SYMBOLS: [&str; 21] = [
+    "==", "!=", "<>", "<=", ">=", "&&", "||", "=", "<", ">", "!", "+", "-", "*", "/", "%", "&",
+    "~", "(", ")", ",",
+];
+
+/// A value an expression evaluates to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    Boolean(bool),
+    DateTime(NaiveDateTime),
+    Duration(Duration),
+    Empty,
+}
+
+impl Value {
+    pub fn from_cell(cell: &Cell) -> Value {
+        match cell {
+            Cell::Text(s) => Value::Text(s.clone()),
+            Cell::Number(n) | Cell::FormattedNumber(n, _) => Value::Number(*n),
+            Cell::Boolean(b) => Value::Boolean(*b),
+            Cell::DateTime(d) => Value::DateTime(*d),
+            Cell::Formula(_, v) => Value::from_cell(v),
+            Cell::Empty => Value::Empty,
+        }
+    }
+
+    /// Converts the value to a cell. Durations become a number of days, as
+    /// spreadsheets count them.
+    pub fn to_cell(&self) -> Cell {
+        match self {
+            Value::Text(s) => Cell::Text(s.clone()),
+            Value::Number(n) if n.is_finite() => Cell::Number(*n),
+            Value::Boolean(b) => Cell::Boolean(*b),
+            Value::DateTime(d) => Cell::DateTime(*d),
+            Value::Duration(d) => Cell::Number(duration_days(d)),
+            Value::Number(_) | Value::Empty => Cell::Empty,
+        }
+    }
+
+    /// Whether the value counts as true: non-zero numbers, non-empty text,
+    /// dates and `true`.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(s) => !s.is_empty(),
+            Value::Number(n) => *n != 0.0,
+            Value::Boolean(b) => *b,
+            Value::DateTime(_) => true,
+            Value::Duration(d) => !d.is_zero(),
+            Value::Empty => false,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Text(s) => parse_number(s.trim()),
+            _ => None,
+        }
+    }
+
+    fn as_date(&self) -> Option<NaiveDateTime> {
+        match self {
+            Value::DateTime(d) => Some(*d),
+            Value::Text(s) => parse_date(s.trim(), None),
+            _ => None,
+        }
+    }
+
+    /// Orders two values, converting text to a number or date when compared
+    /// with one, and comparing text with text ignoring case, as spreadsheets
+    /// do. Values that can't be compared give `None`.
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Number(_), _) | (_, Value::Number(_)) => {
+                self.as_number()?.partial_cmp(&other.as_number()?)
+            }
+            (Value::DateTime(_), _) | (_, Value::DateTime(_)) => {
+                Some(self.as_date()?.cmp(&other.as_date()?))
+            }
+            (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(b)),
+            (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+            (Value::Duration(_), _) | (_, Value::Duration(_)) => None,
+            (Value::Boolean(_), _) | (_, Value::Boolean(_)) => None,
+            // Text on both sides is compared as dates if both are, e.g. the
+            // RFC 2822 dates of feeds
+            _ => match (self.as_date(), other.as_date()) {
+                (Some(a), Some(b)) => Some(a.cmp(&b)),
+                _ => Some(
+                    self.to_string()
+                        .to_lowercase()
+                        .cmp(&other.to_string().to_lowercase()),
+                ),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Boolean(b) => write!(f, "{}", Cell::Boolean(*b)),
+            Value::DateTime(d) => write!(f, "{}", Cell::DateTime(*d)),
+            Value::Duration(d) => write!(f, "{}s", d.num_seconds()),
+            Value::Empty => Ok(()),
+        }
+    }
+}
+
+fn duration_days(duration: &Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 86_400_000.0
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Duration(Duration),
+    Text(String),
+    Word(String),
+    /// A column heading in brackets, e.g. `[Size (bytes)]`.
+    Column(String),
+    /// A column number, e.g. `$2`.
+    Index(usize),
+    Symbol(&'static str),
+}
+
+/// Reads the unit after a number, if any, returning the token and the length
+/// read (including any space before the unit).
+fn number_with_unit(n: f64, after: &str) -> (Token, usize) {
+    let trimmed = after.trim_start();
+    let unit_len = trimmed
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(trimmed.len());
+    let unit = trimmed[..unit_len].to_ascii_lowercase();
+    let len = after.len() - trimmed.len() + unit_len;
+
+    if let Some((_, factor)) = SIZE_UNITS.iter().find(|(u, _)| *u == unit) {
+        (Token::Number(n * factor), len)
+    } else if let Some((_, seconds)) = DURATION_UNITS.iter().find(|(u, _)| *u == unit) {
+        let milliseconds = (n * seconds * 1000.0).round() as i64;
+        (Token::Duration(Duration::milliseconds(milliseconds)), len)
+    } else {
+        (Token::Number(n), 0)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while let Some(c) = source[pos..].chars().next() {
+        let rest = &source[pos..];
+        let start = pos;
+
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+
+        let token = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let n = rest[..len]
+                .parse::<f64>()
+                .map_err(|_| (start, "invalid number".to_string()))?;
+            let (token, unit_len) = number_with_unit(n, &rest[len..]);
+
+            pos += len + unit_len;
+            token
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            let mut escaped = false;
+            let mut end = None;
+
+            for (i, ch) in rest.char_indices().skip(1) {
+                if escaped {
+                    text.push(ch);
+                    escaped = false;
+                } else if ch == '\\' {
+                    escaped = true;
+                } else if ch == c {
+                    end = Some(i);
+                    break;
+                } else {
+                    text.push(ch);
+                }
+            }
+
+            let end = end.ok_or((start, "unterminated string".to_string()))?;
+            pos += end + 1;
+            Token::Text(text)
+        } else if c == '[' {
+            let end = rest.find(']').ok_or((
+                start,
+                "unterminated column name, expected \"]\"".to_string(),
+            ))?;
+            pos += end + 1;
+            Token::Column(rest[1..end].trim().to_string())
+        } else if c == '$' {
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - 1);
+            let n = rest[1..1 + len]
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or((start, "expected a column number after \"$\"".to_string()))?;
+            pos += 1 + len;
+            Token::Index(n)
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            pos += len;
+            Token::Word(rest[..len].to_string())
+        } else {
+            let symbol = SYMBOLS
+                .into_iter()
+                .find(|s| rest.starts_with(s))
+                .ok_or((start, format!("unexpected character \"{}\"", c)))?;
+            pos += symbol.len();
+            Token::Symbol(symbol)
+        };
+
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Concat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Now,
+    Today,
+    Date,
+    Lower,
+    Upper,
+    Trim,
+    Len,
+    Abs,
+    Round,
+    Year,
+    Month,
+    Day,
+    If,
+}
+
+/// Function names, with the least and most arguments each takes.
+const FUNCTIONS: [(&str, Function, usize, usize); 13] = [
+    ("now", Function::Now, 0, 0),
+    ("today", Function::Today, 0, 0),
+    ("date", Function::Date, 1, 1),
+    ("lower", Function::Lower, 1, 1),
+    ("upper", Function::Upper, 1, 1),
+    ("trim", Function::Trim, 1, 1),
+    ("len", Function::Len, 1, 1),
+    ("abs", Function::Abs, 1, 1),
+    ("round", Function::Round, 1, 2),
+    ("year", Function::Year, 1, 1),
+    ("month", Function::Month, 1, 1),
+    ("day", Function::Day, 1, 1),
+    ("if", Function::If, 2, 3),
+];
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Value),
+    /// A column by heading (or `$N` for a number), resolved to an index by
+    /// [`Expression::bind`].
+    Column {
+        reference: String,
+        position: usize,
+        index: Option<usize>,
+    },
+    Not(Box<Node>),
+    Negate(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Matches(Box<Node>, Regex),
+    Call(Function, Vec<Node>),
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+}
+
+type ParseResult = Result<Node, (usize, String)>;
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(t, _)| t)
+    }
+
+    /// Position of the next token, or the end of the source.
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map_or(self.end, |(_, p)| *p)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.index += 1;
+        token
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, (usize, String)> {
+        let found = match self.peek() {
+            Some(_) => "",
+            None => " at end of expression",
+        };
+
+        Err((self.position(), format!("{}{}", message, found)))
+    }
+
+    fn eat_symbol(&mut self, symbols: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(s)) if symbols.contains(s) => {
+                let s = *s;
+                self.index += 1;
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
+    fn eat_word(&mut self, words: &[&str]) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(w)) if words.iter().any(|k| w.eq_ignore_ascii_case(k)) => {
+                let w = w.to_ascii_lowercase();
+                self.index += 1;
+                Some(w)
+            }
+            _ => None,
+        }
+    }
+
+    fn or(&mut self) -> ParseResult {
+        let mut node = self.and()?;
+
+        while self.eat_symbol(&["||"]).is_some() || self.eat_word(&["or"]).is_some() {
+            node = Node::Binary(BinaryOp::Or, Box::new(node), Box::new(self.and()?));
+        }
+
+        Ok(node)
+    }
+
+    fn and(&mut self) -> ParseResult {
+        let mut node = self.not()?;
+
+        while self.eat_symbol(&["&&"]).is_some() || self.eat_word(&["and"]).is_some() {
+            node = Node::Binary(BinaryOp::And, Box::new(node), Box::new(self.not()?));
+        }
+
+        Ok(node)
+    }
+
+    fn not(&mut self) -> ParseResult {
+        if self.eat_symbol(&["!"]).is_some() || self.eat_word(&["not"]).is_some() {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> ParseResult {
+        let left = self.sum()?;
+
+        let op = if let Some(s) = self.eat_symbol(&["=", "==", "!=", "<>", "<", "<=", ">", ">="]) {
+            match s {
+                "=" | "==" => BinaryOp::Eq,
+                "!=" | "<>" => BinaryOp::Ne,
+                "<" => BinaryOp::Lt,
+                "<=" => BinaryOp::Le,
+                ">" => BinaryOp::Gt,
+                _ => BinaryOp::Ge,
+            }
+        } else if let Some(w) = self.eat_word(&["contains", "startswith", "endswith"]) {
+            match w.as_str() {
+                "contains" => BinaryOp::Contains,
+                "startswith" => BinaryOp::StartsWith,
+                _ => BinaryOp::EndsWith,
+            }
+        } else if self.eat_symbol(&["~"]).is_some() || self.eat_word(&["matches"]).is_some() {
+            let position = self.position();
+            let Some(Token::Text(pattern)) = self.peek().cloned() else {
+                return self.error("expected a quoted regular expression");
+            };
+            self.index += 1;
+
+            let regex = Regex::new(&pattern).map_err(|e| {
+                // The regex crate's errors draw their own pointer above
+                // the message, which is on the last line
+                let message = e.to_string();
+                let reason = message.lines().last().unwrap_or_default();
+                (
+                    position,
+                    format!(
+                        "invalid regular expression: {}",
+                        reason.trim_start_matches("error: ")
+                    ),
+                )
+            })?;
+
+            return Ok(Node::Matches(Box::new(left), regex));
+        } else {
+            return Ok(left);
+        };
+
+        Ok(Node::Binary(op, Box::new(left), Box::new(self.sum()?)))
+    }
+
+    fn sum(&mut self) -> ParseResult {
+        let mut node = self.product()?;
+
+        while let Some(s) = self.eat_symbol(&["+", "-", "&"]) {
+            let op = match s {
+                "+" => BinaryOp::Add,
+                "-" => BinaryOp::Sub,
+                _ => BinaryOp::Concat,
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+
+        Ok(node)
+    }
+
+    fn product(&mut self) -> ParseResult {
+        let mut node = self.unary()?;
+
+        while let Some(s) = self.eat_symbol(&["*", "/", "%"]) {
+            let op = match s {
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> ParseResult {
+        match self.eat_symbol(&["-"]) {
+            Some(_) => Ok(Node::Negate(Box::new(self.unary()?))),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> ParseResult {
+        let position = self.position();
+
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Node::Literal(Value::Number(n))),
+            Some(Token::Duration(d)) => Ok(Node::Literal(Value::Duration(d))),
+            Some(Token::Text(s)) => Ok(Node::Literal(Value::Text(s))),
+            Some(Token::Column(reference)) => Ok(Node::Column {
+                reference,
+                position,
+                index: None,
+            }),
+            Some(Token::Index(n)) => Ok(Node::Column {
+                reference: format!("${}", n),
+                position,
+                index: None,
+            }),
+            Some(Token::Symbol("(")) => {
+                let node = self.or()?;
+                match self.eat_symbol(&[")"]) {
+                    Some(_) => Ok(node),
+                    None => self.error("expected \")\""),
+                }
+            }
+            Some(Token::Word(w)) if self.eat_symbol(&["("]).is_some() => self.call(&w, position),
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("true") => {
+                Ok(Node::Literal(Value::Boolean(true)))
+            }
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("false") => {
+                Ok(Node::Literal(Value::Boolean(false)))
+            }
+            Some(Token::Word(reference)) => Ok(Node::Column {
+                reference,
+                position,
+                index: None,
+            }),
+            _ => {
+                self.index -= 1;
+                self.error("expected a value, column or \"(\"")
+            }
+        }
+    }
+
+    /// Parses the arguments of a call, after the opening parenthesis.
+    fn call(&mut self, name: &str, position: usize) -> ParseResult {
+        let Some((_, function, min, max)) = FUNCTIONS
+            .into_iter()
+            .find(|(n, ..)| n.eq_ignore_ascii_case(name))
+        else {
+            return Err((position, format!("unknown function \"{}\"", name)));
+        };
+
+        let mut args = Vec::new();
+        if self.eat_symbol(&[")"]).is_none() {
+            loop {
+                args.push(self.or()?);
+                match self.eat_symbol(&[",", ")"]) {
+                    Some(",") => {}
+                    Some(_) => break,
+                    None => return self.error("expected \",\" or \")\""),
+                }
+            }
+        }
+
+        if args.len() < min || args.len() > max {
+            let expected = match min == max {
+                true => min.to_string(),
+                false => format!("{} to {}", min, max),
+            };
+            return Err((
+                position,
+                format!(
+                    "{}() takes {} arguments, got {}",
+                    name,
+                    expected,
+                    args.len()
+                ),
+            ));
+        }
+
+        Ok(Node::Call(function, args))
+    }
+}
+
+/// An expression over the columns of a row, e.g.
+/// `[Size (bytes)] > 10MB and Type = "File"`.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+    /// Time `now()` and `today()` are taken from, fixed when parsed so that
+    /// every row sees the same one.
+    now: NaiveDateTime,
+}
+
+/// Describes an error in an expression, pointing at the position it was
+/// found.
+fn error_at(source: &str, position: usize, message: &str) -> String {
+    let column = source[..position].chars().count();
+
+    format!(
+        "column {}: {}\n    {}\n    {}^",
+        column + 1,
+        message,
+        source,
+        " ".repeat(column)
+    )
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, String> {
+        let tokens = tokenize(source).map_err(|(p, e)| error_at(source, p, &e))?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            end: source.len(),
+        };
+
+        let root = parser.or().map_err(|(p, e)| error_at(source, p, &e))?;
+        if parser.peek().is_some() {
+            return Err(error_at(source, parser.position(), "expected an operator"));
+        }
+
+        Ok(Expression {
+            source: source.to_string(),
+            root,
+            now: Local::now().naive_local(),
+        })
+    }
+
+    /// Resolves the columns the expression refers to in a table.
+    pub fn bind(&mut self, table: &Table) -> Result<(), String> {
+        fn bind_node(node: &mut Node, table: &Table) -> Result<(), (usize, String)> {
+            match node {
+                Node::Column {
+                    reference,
+                    position,
+                    index,
+                } => {
+                    *index = match reference.strip_prefix('$') {
+                        Some(n) => n
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| *n <= table.width())
+                            .map(|n| n - 1),
+                        None => table.column_index(reference),
+                    };
+
+                    match index {
+                        Some(_) => Ok(()),
+                        None => Err((*position, format!("unknown column \"{}\"", reference))),
+                    }
+                }
+                Node::Literal(_) => Ok(()),
+                Node::Not(n) | Node::Negate(n) | Node::Matches(n, _) => bind_node(n, table),
+                Node::Binary(_, a, b) => {
+                    bind_node(a, table)?;
+                    bind_node(b, table)
+                }
+                Node::Call(_, args) => args.iter_mut().try_for_each(|a| bind_node(a, table)),
+            }
+        }
+
+        bind_node(&mut self.root, table).map_err(|(p, e)| error_at(&self.source, p, &e))
+    }
+
+    /// Evaluates the expression for a row of the table it was bound to.
+    pub fn evaluate(&self, row: &Row) -> Value {
+        self.evaluate_node(&self.root, row)
+    }
+
+    fn evaluate_node(&self, node: &Node, row: &Row) -> Value {
+        match node {
+            Node::Literal(v) => v.clone(),
+            Node::Column { index, .. } => index
+                .and_then(|i| row.cells.get(i))
+                .map_or(Value::Empty, Value::from_cell),
+            Node::Not(n) => Value::Boolean(!self.evaluate_node(n, row).is_truthy()),
+            Node::Negate(n) => match self.evaluate_node(n, row) {
+                Value::Duration(d) => Value::Duration(-d),
+                v => v.as_number().map_or(Value::Empty, |n| Value::Number(-n)),
+            },
+            Node::Matches(n, regex) => {
+                Value::Boolean(regex.is_match(&self.evaluate_node(n, row).to_string()))
+            }
+            Node::Binary(BinaryOp::And, a, b) => Value::Boolean(
+                self.evaluate_node(a, row).is_truthy() && self.evaluate_node(b, row).is_truthy(),
+            ),
+            Node::Binary(BinaryOp::Or, a, b) => Value::Boolean(
+                self.evaluate_node(a, row).is_truthy() || self.evaluate_node(b, row).is_truthy(),
+            ),
+            Node::Binary(op, a, b) => {
+                binary(*op, self.evaluate_node(a, row), self.evaluate_node(b, row))
+            }
+            Node::Call(function, args) => {
+                let args: Vec<Value> = args.iter().map(|a| self.evaluate_node(a, row)).collect();
+                self.call(*function, args)
+            }
+        }
+    }
+
+    /// Writes the expression as a spreadsheet formula over the columns it was
+    /// bound to. Fails for `matches`, which spreadsheets have no equivalent
+    /// of.
+    pub fn to_formula(&self) -> Result<Formula, String> {
+        let mut parts = Vec::new();
+        formula_parts(&self.root, &mut parts)?;
+
+        Ok(Formula { parts })
+    }
+
+    fn call(&self, function: Function, args: Vec<Value>) -> Value {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Empty);
+        let date_part = |part: fn(&NaiveDateTime) -> u32| {
+            arg(0)
+                .as_date()
+                .map_or(Value::Empty, |d| Value::Number(part(&d) as f64))
+        };
+
+        match function {
+            Function::Now => Value::DateTime(self.now),
+            Function::Today => Value::DateTime(self.now.date().into()),
+            Function::Date => arg(0).as_date().map_or(Value::Empty, Value::DateTime),
+            Function::Lower => Value::Text(arg(0).to_string().to_lowercase()),
+            Function::Upper => Value::Text(arg(0).to_string().to_uppercase()),
+            Function::Trim => Value::Text(arg(0).to_string().trim().to_string()),
+            Function::Len => Value::Number(arg(0).to_string().chars().count() as f64),
+            Function::Abs => arg(0)
+                .as_number()
+                .map_or(Value::Empty, |n| Value::Number(n.abs())),
+            Function::Round => {
+                let places = arg(1).as_number().unwrap_or(0.0).clamp(-15.0, 15.0) as i32;
+                let factor = 10f64.powi(places);
+
+                arg(0).as_number().map_or(Value::Empty, |n| {
+                    Value::Number((n * factor).round() / factor)
+                })
+            }
+            Function::Year => arg(0)
+                .as_date()
+                .map_or(Value::Empty, |d| Value::Number(d.year() as f64)),
+            Function::Month => date_part(|d| d.month()),
+            Function::Day => date_part(|d| d.day()),
+            Function::If => match arg(0).is_truthy() {
+                true => arg(1),
+                false => arg(2),
+            },
+        }
+    }
+}
+
+fn push_formula_text(parts: &mut Vec<FormulaPart>, text: &str) {
+    match parts.last_mut() {
+        Some(FormulaPart::Text(t)) => t.push_str(text),
+        _ => parts.push(FormulaPart::Text(text.to_string())),
+    }
+}
+
+/// Writes a function call, or any text around and between formulas.
+fn push_formula_call(
+    parts: &mut Vec<FormulaPart>,
+    open: &str,
+    args: &[&Node],
+    separator: &str,
+    close: &str,
+) -> Result<(), String> {
+    push_formula_text(parts, open);
+    for (i, a) in args.iter().enumerate() {
+        if i > 0 {
+            push_formula_text(parts, separator);
+        }
+        formula_parts(a, parts)?;
+    }
+    push_formula_text(parts, close);
+
+    Ok(())
+}
+
+fn formula_parts(node: &Node, parts: &mut Vec<FormulaPart>) -> Result<(), String> {
+    match node {
+        Node::Literal(v) => push_formula_text(
+            parts,
+            &match v {
+                Value::Text(s) => format!("\"{}\"", s.replace('"', "\"\"")),
+                Value::Number(n) => n.to_string(),
+                Value::Boolean(true) => "TRUE".to_string(),
+                Value::Boolean(false) => "FALSE".to_string(),
+                Value::Duration(d) => duration_days(d).to_string(),
+                Value::DateTime(_) | Value::Empty => "\"\"".to_string(),
+            },
+        ),
+        Node::Column {
+            reference, index, ..
+        } => match index {
+            Some(i) => parts.push(FormulaPart::Column(*i)),
+            None => return Err(format!("unknown column \"{}\"", reference)),
+        },
+        Node::Not(n) => push_formula_call(parts, "NOT(", &[n], "", ")")?,
+        Node::Negate(n) => push_formula_call(parts, "-(", &[n], "", ")")?,
+        Node::Matches(..) => {
+            return Err("\"matches\" can't be written as a spreadsheet formula".to_string())
+        }
+        Node::Binary(op, a, b) => {
+            let (a, b) = (a.as_ref(), b.as_ref());
+
+            match op {
+                BinaryOp::And => push_formula_call(parts, "AND(", &[a, b], ",", ")")?,
+                BinaryOp::Or => push_formula_call(parts, "OR(", &[a, b], ",", ")")?,
+                BinaryOp::Rem => push_formula_call(parts, "MOD(", &[a, b], ",", ")")?,
+                BinaryOp::Contains => {
+                    push_formula_call(parts, "ISNUMBER(SEARCH(", &[b, a], ",", "))")?
+                }
+                BinaryOp::StartsWith | BinaryOp::EndsWith => {
+                    let side = match op {
+                        BinaryOp::StartsWith => "(LEFT(",
+                        _ => "(RIGHT(",
+                    };
+                    push_formula_call(parts, side, &[a, b], ",LEN(", "))=")?;
+                    push_formula_call(parts, "", &[b], "", ")")?;
+                }
+                _ => {
+                    let symbol = match op {
+                        BinaryOp::Eq => "=",
+                        BinaryOp::Ne => "<>",
+                        BinaryOp::Lt => "<",
+                        BinaryOp::Le => "<=",
+                        BinaryOp::Gt => ">",
+                        BinaryOp::Ge => ">=",
+                        BinaryOp::Add => "+",
+                        BinaryOp::Sub => "-",
+                        BinaryOp::Mul => "*",
+                        BinaryOp::Div => "/",
+                        _ => "&",
+                    };
+                    push_formula_call(parts, "(", &[a, b], symbol, ")")?;
+                }
+            }
+        }
+        // DATEVALUE only reads text, while date cells are already numbers
+        Node::Call(Function::Date, args) if args.len() == 1 => {
+            let x = &args[0];
+            push_formula_call(parts, "IF(ISNUMBER(", &[x], "", "),")?;
+            push_formula_call(parts, "", &[x], "", ",DATEVALUE(")?;
+            push_formula_call(parts, "", &[x], "", "))")?;
+        }
+        Node::Call(function, args) => {
+            let name = match function {
+                Function::Now => "NOW",
+                Function::Today => "TODAY",
+                Function::Date => "DATEVALUE",
+                Function::Lower => "LOWER",
+                Function::Upper => "UPPER",
+                Function::Trim => "TRIM",
+                Function::Len => "LEN",
+                Function::Abs => "ABS",
+                Function::Round => "ROUND",
+                Function::Year => "YEAR",
+                Function::Month => "MONTH",
+                Function::Day => "DAY",
+                Function::If => "IF",
+            };
+            let args: Vec<&Node> = args.iter().collect();
+            let missing = match (function, args.len()) {
+                (Function::Round, 1) => ",0",
+                (Function::If, 2) => ",\"\"",
+                _ => "",
+            };
+
+            push_formula_call(parts, &format!("{}(", name), &args, ",", "")?;
+            push_formula_text(parts, &format!("{})", missing));
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies an operator other than `and`/`or`. Arithmetic on values that
+/// aren't numbers (or dates and durations) gives an empty value.
+fn binary(op: BinaryOp, a: Value, b: Value) -> Value {
+    let ignoring_case = |f: fn(&str, &str) -> bool| {
+        Value::Boolean(f(
+            &a.to_string().to_lowercase(),
+            &b.to_string().to_lowercase(),
+        ))
+    };
+
+    match op {
+        BinaryOp::Eq => Value::Boolean(a.compare(&b) == Some(Ordering::Equal)),
+        BinaryOp::Ne => Value::Boolean(a.compare(&b) != Some(Ordering::Equal)),
+        BinaryOp::Lt => Value::Boolean(a.compare(&b) == Some(Ordering::Less)),
+        BinaryOp::Le => Value::Boolean(matches!(
+            a.compare(&b),
+            Some(Ordering::Less | Ordering::Equal)
+        )),
+        BinaryOp::Gt => Value::Boolean(a.compare(&b) == Some(Ordering::Greater)),
+        BinaryOp::Ge => Value::Boolean(matches!(
+            a.compare(&b),
+            Some(Ordering::Greater | Ordering::Equal)
+        )),
+        BinaryOp::Contains => ignoring_case(|a, b| a.contains(b)),
+        BinaryOp::StartsWith => ignoring_case(|a, b| a.starts_with(b)),
+        BinaryOp::EndsWith => ignoring_case(|a, b| a.ends_with(b)),
+        BinaryOp::Concat => Value::Text(format!("{}{}", a, b)),
+        _ => arithmetic(op, a, b),
+    }
+}
+
+fn arithmetic(op: BinaryOp, a: Value, b: Value) -> Value {
+    match (op, &a, &b) {
+        (BinaryOp::Add | BinaryOp::Sub, Value::Duration(x), Value::Duration(y)) => {
+            Value::Duration(match op {
+                BinaryOp::Add => *x + *y,
+                _ => *x - *y,
+            })
+        }
+        (BinaryOp::Add, Value::Duration(x), _) => b
+            .as_date()
+            .map_or(Value::Empty, |d| Value::DateTime(d + *x)),
+        (BinaryOp::Add | BinaryOp::Sub, _, Value::Duration(x)) => {
+            a.as_date().map_or(Value::Empty, |d| {
+                Value::DateTime(match op {
+                    BinaryOp::Add => d + *x,
+                    _ => d - *x,
+                })
+            })
+        }
+        (BinaryOp::Sub, Value::DateTime(_), _) | (BinaryOp::Sub, _, Value::DateTime(_)) => {
+            match (a.as_date(), b.as_date()) {
+                (Some(x), Some(y)) => Value::Duration(x - y),
+                _ => Value::Empty,
+            }
+        }
+        (BinaryOp::Mul | BinaryOp::Div, Value::Duration(x), _) => {
+            let Some(n) = b.as_number().filter(|n| op == BinaryOp::Mul || *n != 0.0) else {
+                return Value::Empty;
+            };
+            let milliseconds = x.num_milliseconds() as f64;
+
+            Value::Duration(Duration::milliseconds(match op {
+                BinaryOp::Mul => (milliseconds * n) as i64,
+                _ => (milliseconds / n) as i64,
+            }))
+        }
+        _ => match (a.as_number(), b.as_number()) {
+            (Some(x), Some(y)) => match op {
+                BinaryOp::Add => Value::Number(x + y),
+                BinaryOp::Sub => Value::Number(x - y),
+                BinaryOp::Mul => Value::Number(x * y),
+                BinaryOp::Div | BinaryOp::Rem if y == 0.0 => Value::Empty,
+                BinaryOp::Div => Value::Number(x / y),
+                _ => Value::Number(x % y),
+            },
+            _ => Value::Empty,
+        },
+    }
+}
+
+/// Keeps the rows of a table matching every filter expression.
+pub fn filter_table(mut table: Table, filters: &[String]) -> Result<Table, String> {
+    let expressions = filters
+        .iter()
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| {
+            let mut e = Expression::parse(f)?;
+            e.bind(&table)?;
+            Ok(e)
+        })
+        .collect::<Result<Vec<Expression>, String>>()?;
+
+    table
+        .rows
+        .retain(|r| expressions.iter().all(|e| e.evaluate(r).is_truthy()));
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Evaluates an expression for a row of cells under the headings A, B.
+    fn evaluate(source: &str, cells: Vec<Cell>) -> Value {
+        let table = Table::new().with_headings(vec!["A", "B"]);
+        let mut expression = Expression::parse(source).unwrap();
+        expression.bind(&table).unwrap();
+        expression.evaluate(&Row::new().with_cells(cells))
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("1 + 2 * 3", vec![]), Value::Number(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3", vec![]), Value::Number(9.0));
+        assert_eq!(evaluate("10 - 4 - 3", vec![]), Value::Number(3.0));
+        assert_eq!(evaluate("-2 * 3 + 7 % 4", vec![]), Value::Number(-3.0));
+        assert_eq!(evaluate("1 + 1 = 2", vec![]), Value::Boolean(true));
+        assert_eq!(
+            evaluate("1 = 1 or 1 = 2 and 1 = 2", vec![]),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate("not 1 = 2 and 2 > 1", vec![]),
+            Value::Boolean(true)
+        );
+        assert_eq!(evaluate("!(1 = 1 || 1 = 2)", vec![]), Value::Boolean(false));
+        assert_eq!(
+            evaluate("1 + 2 & \"a\"", vec![]),
+            Value::Text("3a".to_string())
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(evaluate("10MB", vec![]), Value::Number(1e7));
+        assert_eq!(evaluate("2 KiB", vec![]), Value::Number(2048.0));
+        assert_eq!(evaluate("1 gb > 999 mb", vec![]), Value::Boolean(true));
+        assert_eq!(
+            evaluate("1.5h", vec![]),
+            Value::Duration(Duration::minutes(90))
+        );
+        assert_eq!(
+            evaluate("1w - 2d", vec![]),
+            Value::Duration(Duration::days(5))
+        );
+        assert_eq!(
+            evaluate("[A] + 1d", vec![Cell::Text("2024-01-31".to_string())]),
+            Value::DateTime(date(2024, 2, 1))
+        );
+    }
+
+    #[test]
+    fn compare_dates() {
+        let feed_date = |d: &str| vec![Cell::Text(d.to_string())];
+
+        assert_eq!(
+            evaluate(
+                "[A] > \"2024-01-01\"",
+                feed_date("Tue, 02 Jan 2024 08:00:00 +0000")
+            ),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate(
+                "[A] < \"2024-01-01\"",
+                feed_date("Sun, 31 Dec 2023 23:00:00 +0000")
+            ),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate(
+                "[A] >= [B]",
+                vec![
+                    Cell::DateTime(date(2024, 3, 1)),
+                    Cell::Text("2024-02-29".to_string())
+                ]
+            ),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate(
+                "[A] - [B] = 2d",
+                vec![
+                    Cell::DateTime(date(2024, 3, 2)),
+                    Cell::DateTime(date(2024, 2, 29))
+                ]
+            ),
+            Value::Boolean(true)
+        );
+    }
+
+    /// Writes an expression over the columns A, B as an R1C1 formula.
+    fn formula(source: &str) -> String {
+        let table = Table::new().with_headings(vec!["A", "B"]);
+        let mut expression = Expression::parse(source).unwrap();
+        expression.bind(&table).unwrap();
+        expression.to_formula().unwrap().to_r1c1()
+    }
+
+    #[test]
+    fn formulas() {
+        assert_eq!(formula("[A] * 2 + 1"), "=((RC1*2)+1)");
+        assert_eq!(formula("[A] = \"open\""), "=(RC1=\"open\")");
+        // Date cells are numbers, which DATEVALUE doesn't take
+        assert_eq!(
+            formula("date([B]) >= today()"),
+            "=(IF(ISNUMBER(RC2),RC2,DATEVALUE(RC2))>=TODAY())"
+        );
+        assert!(Expression::parse("[A] ~ \"x\"")
+            .unwrap()
+            .to_formula()
+            .is_err());
+    }
+
+    #[test]
+    fn compare_numbers_and_text() {
+        let text = |t: &str| Cell::Text(t.to_string());
+
+        // Text is read as a number when compared with one
+        assert_eq!(evaluate("[A] > 9", vec![text("10")]), Value::Boolean(true));
+        assert_eq!(
+            evaluate("[A] = [B]", vec![text("1.0"), Cell::Number(1.0)]),
+            Value::Boolean(true)
+        );
+        // and compared as text with text
+        assert_eq!(
+            evaluate("[A] < [B]", vec![text("apple"), text("banana")]),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate("[A] > [B]", vec![text("10"), text("9")]),
+            Value::Boolean(false)
+        );
+        // Text is compared ignoring case, as by the formula written for it
+        assert_eq!(
+            evaluate("[A] = \"open\"", vec![text("Open")]),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            evaluate("[A] != \"OPEN\"", vec![text("open")]),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            evaluate("[A] < [B]", vec![text("apple"), text("Banana")]),
+            Value::Boolean(true)
+        );
+        // Values that can't be compared are neither equal nor ordered
+        assert_eq!(
+            evaluate("[A] < 5", vec![text("abc")]),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            evaluate("[A] = 1d", vec![text("abc")]),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            evaluate("[A] != 1d", vec![text("abc")]),
+            Value::Boolean(true)
+        );
+    }
+}
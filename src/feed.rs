@@ -0,0 +1,698 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use std::{collections::HashSet, fmt};
+
+use crate::html::tag_attribute;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedKind {
+    Rss2,
+    Rss1,
+    Atom,
+    Json,
+}
+
+impl fmt::Display for FeedKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FeedKind::Rss2 => "RSS 2.0",
+                FeedKind::Rss1 => "RSS 1.0 (RDF)",
+                FeedKind::Atom => "Atom 1.0",
+                FeedKind::Json => "JSON Feed",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedColumn {
+    Date,
+    Title,
+    Description,
+    Link,
+    Author,
+    Categories,
+    Guid,
+    Comments,
+    EnclosureUrl,
+    EnclosureLength,
+    EnclosureType,
+    Source,
+    ItunesDuration,
+    DcCreator,
+    Feed,
+}
+
+impl FeedColumn {
+    pub const ALL: [FeedColumn; 15] = [
+        FeedColumn::Date,
+        FeedColumn::Title,
+        FeedColumn::Description,
+        FeedColumn::Link,
+        FeedColumn::Author,
+        FeedColumn::Categories,
+        FeedColumn::Guid,
+        FeedColumn::Comments,
+        FeedColumn::EnclosureUrl,
+        FeedColumn::EnclosureLength,
+        FeedColumn::EnclosureType,
+        FeedColumn::Source,
+        FeedColumn::ItunesDuration,
+        FeedColumn::DcCreator,
+        FeedColumn::Feed,
+    ];
+
+    /// Name used to select the column on the command line.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            FeedColumn::Date => "date",
+            FeedColumn::Title => "title",
+            FeedColumn::Description => "description",
+            FeedColumn::Link => "link",
+            FeedColumn::Author => "author",
+            FeedColumn::Categories => "categories",
+            FeedColumn::Guid => "guid",
+            FeedColumn::Comments => "comments",
+            FeedColumn::EnclosureUrl => "enclosure-url",
+            FeedColumn::EnclosureLength => "enclosure-length",
+            FeedColumn::EnclosureType => "enclosure-type",
+            FeedColumn::Source => "source",
+            FeedColumn::ItunesDuration => "itunes-duration",
+            FeedColumn::DcCreator => "dc-creator",
+            FeedColumn::Feed => "feed",
+        }
+    }
+
+    pub const fn heading(&self) -> &'static str {
+        match self {
+            FeedColumn::Date => "Date",
+            FeedColumn::Title => "Title",
+            FeedColumn::Description => "Description",
+            FeedColumn::Link => "Link",
+            FeedColumn::Author => "Author",
+            FeedColumn::Categories => "Categories",
+            FeedColumn::Guid => "GUID",
+            FeedColumn::Comments => "Comments",
+            FeedColumn::EnclosureUrl => "Enclosure URL",
+            FeedColumn::EnclosureLength => "Enclosure Length",
+            FeedColumn::EnclosureType => "Enclosure Type",
+            FeedColumn::Source => "Source",
+            FeedColumn::ItunesDuration => "Duration",
+            FeedColumn::DcCreator => "Creator",
+            FeedColumn::Feed => "Feed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FeedColumn> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Parses a comma-separated list of column names, e.g. `date,title,author`.
+    pub fn parse_list(list: &str) -> Result<Vec<FeedColumn>, String> {
+        list.split(',')
+            .map(|name| {
+                Self::from_name(name).ok_or(format!("unknown feed column \"{}\"", name.trim()))
+            })
+            .collect()
+    }
+}
+
+/// Where to put the channel-level metadata of a feed, if anywhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelInfo {
+    None,
+    Header,
+    Sheet,
+}
+
+impl ChannelInfo {
+    pub const ALL: [ChannelInfo; 3] = [ChannelInfo::None, ChannelInfo::Header, ChannelInfo::Sheet];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            ChannelInfo::None => "none",
+            ChannelInfo::Header => "header",
+            ChannelInfo::Sheet => "sheet",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ChannelInfo> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// How HTML in item descriptions is written to cells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DescriptionMode {
+    /// The markup exactly as it appears in the feed.
+    Raw,
+    /// Converted to plain text.
+    Text,
+    /// Converted to plain text, then cut to the given number of characters.
+    Truncate(usize),
+    /// Converted to plain text, with the first link in a column of its own.
+    FirstLink,
+}
+
+impl DescriptionMode {
+    /// Parses `raw`, `text`, `link` or `truncate:N`, where N is at least 1.
+    pub fn from_name(name: &str) -> Option<DescriptionMode> {
+        let name = name.trim().to_ascii_lowercase();
+
+        match name.as_str() {
+            "raw" => Some(DescriptionMode::Raw),
+            "text" => Some(DescriptionMode::Text),
+            "link" => Some(DescriptionMode::FirstLink),
+            _ => name
+                .strip_prefix("truncate:")
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| *n > 0)
+                .map(DescriptionMode::Truncate),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            DescriptionMode::Raw => "raw".to_string(),
+            DescriptionMode::Text => "text".to_string(),
+            DescriptionMode::Truncate(n) => format!("truncate:{}", n),
+            DescriptionMode::FirstLink => "link".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedOptions {
+    pub columns: Vec<FeedColumn>,
+    pub channel_info: ChannelInfo,
+    pub description: DescriptionMode,
+    /// Further feeds (paths or URLs) whose items are merged with the source's.
+    pub additional_feeds: Vec<String>,
+}
+
+impl Default for FeedOptions {
+    fn default() -> FeedOptions {
+        FeedOptions {
+            columns: vec![
+                FeedColumn::Date,
+                FeedColumn::Title,
+                FeedColumn::Description,
+                FeedColumn::Link,
+            ],
+            channel_info: ChannelInfo::None,
+            description: DescriptionMode::Raw,
+            additional_feeds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct FeedEnclosure {
+    pub url: Option<String>,
+    pub length: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+#[derive(Default, Clone)]
+pub struct FeedItem {
+    pub date: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub link: Option<String>,
+    pub author: Option<String>,
+    pub categories: Vec<String>,
+    pub guid: Option<String>,
+    pub comments: Option<String>,
+    pub enclosure: Option<FeedEnclosure>,
+    pub source: Option<String>,
+    pub itunes_duration: Option<String>,
+    pub dc_creator: Option<String>,
+    /// Title of the feed the item came from, set when merging feeds.
+    pub feed: Option<String>,
+}
+
+#[derive(Default, Clone)]
+pub struct FeedChannel {
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    pub last_build_date: Option<String>,
+    pub language: Option<String>,
+    pub ttl: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Feed {
+    pub kind: FeedKind,
+    pub channel: FeedChannel,
+    pub items: Vec<FeedItem>,
+}
+
+/// Returns the name of the document's root element, skipping any prolog,
+/// comments and doctype declarations.
+fn root_element(text: &str) -> Option<&str> {
+    let mut rest = text;
+
+    loop {
+        let start = rest.find('<')?;
+        rest = &rest[start + 1..];
+
+        if rest.starts_with('?') || rest.starts_with('!') {
+            let end = if rest.starts_with("!--") {
+                rest.find("-->")? + 3
+            } else {
+                rest.find('>')? + 1
+            };
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+
+        return Some(&rest[..end]);
+    }
+}
+
+pub fn is_opml(text: &str) -> bool {
+    root_element(text.trim_start_matches('\u{feff}')) == Some("opml")
+}
+
+/// Returns the feed URLs listed in an OPML subscription list.
+pub fn opml_feed_urls(text: &str) -> Vec<String> {
+    text.match_indices("<outline")
+        .filter_map(|(i, _)| {
+            let body = &text[i + 1..];
+            let end = body.find('>')?;
+            tag_attribute(&body[..end], "xmlurl")
+        })
+        .collect()
+}
+
+pub fn detect_feed_kind(text: &str) -> Option<FeedKind> {
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    if text.starts_with('{') {
+        return Some(FeedKind::Json);
+    }
+
+    let root = root_element(text)?;
+    let local_name = root.rsplit(':').next().unwrap_or(root);
+
+    match local_name {
+        "rss" => Some(FeedKind::Rss2),
+        "RDF" => Some(FeedKind::Rss1),
+        "feed" => Some(FeedKind::Atom),
+        _ => None,
+    }
+}
+
+fn parse_rss(text: &str) -> Result<(FeedChannel, Vec<FeedItem>), String> {
+    let channel = rss::Channel::read_from(text.as_bytes()).map_err(|e| e.to_string())?;
+
+    let info = FeedChannel {
+        title: Some(channel.title),
+        link: Some(channel.link),
+        description: Some(channel.description),
+        last_build_date: channel.last_build_date.or(channel.pub_date),
+        language: channel.language,
+        ttl: channel.ttl,
+        image: channel.image.map(|i| i.url),
+    };
+
+    let items = channel
+        .items
+        .into_iter()
+        .map(|item| FeedItem {
+            // RSS 1.0 (RDF) feeds carry their dates in the Dublin Core extension
+            date: item.pub_date.or(item
+                .dublin_core_ext
+                .as_ref()
+                .and_then(|dc| dc.dates.first().cloned())),
+            title: item.title,
+            description: item.description,
+            link: item.link,
+            author: item.author,
+            categories: item.categories.into_iter().map(|c| c.name).collect(),
+            guid: item.guid.map(|g| g.value),
+            comments: item.comments,
+            enclosure: item.enclosure.map(|e| FeedEnclosure {
+                url: Some(e.url),
+                length: Some(e.length),
+                mime_type: Some(e.mime_type),
+            }),
+            source: item.source.map(|s| s.title.unwrap_or(s.url)),
+            itunes_duration: item.itunes_ext.and_then(|e| e.duration),
+            dc_creator: item
+                .dublin_core_ext
+                .and_then(|dc| dc.creators.first().cloned()),
+            feed: None,
+        })
+        .collect();
+
+    Ok((info, items))
+}
+
+fn parse_atom(text: &str) -> Result<(FeedChannel, Vec<FeedItem>), String> {
+    let feed = atom_syndication::Feed::read_from(text.as_bytes()).map_err(|e| e.to_string())?;
+
+    let extension = |entry: &atom_syndication::Entry, prefix: &str, name: &str| {
+        entry
+            .extensions
+            .get(prefix)
+            .and_then(|m| m.get(name))
+            .and_then(|v| v.first())
+            .and_then(|e| e.value.clone())
+    };
+
+    let info = FeedChannel {
+        title: Some(feed.title.value.clone()),
+        link: feed
+            .links
+            .iter()
+            .find(|l| l.rel == "alternate")
+            .or(feed.links.first())
+            .map(|l| l.href.clone()),
+        description: feed.subtitle.as_ref().map(|s| s.value.clone()),
+        last_build_date: Some(feed.updated.to_rfc3339()),
+        language: feed.lang.clone(),
+        ttl: None,
+        image: feed.logo.clone().or(feed.icon.clone()),
+    };
+
+    let items = feed
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let link_with_rel = |rel: &str| entry.links.iter().find(|l| l.rel == rel);
+
+            FeedItem {
+                date: Some(entry.published.unwrap_or(entry.updated).to_rfc3339()),
+                title: Some(entry.title.value.clone()),
+                description: match (&entry.summary, &entry.content) {
+                    (Some(summary), _) => Some(summary.value.clone()),
+                    (None, Some(content)) => content.value.clone(),
+                    (None, None) => None,
+                },
+                link: link_with_rel("alternate")
+                    .or(entry.links.first())
+                    .map(|l| l.href.clone()),
+                author: entry.authors.first().map(|a| a.name.clone()),
+                categories: entry
+                    .categories
+                    .iter()
+                    .map(|c| c.label.clone().unwrap_or(c.term.clone()))
+                    .collect(),
+                guid: Some(entry.id.clone()),
+                comments: link_with_rel("replies").map(|l| l.href.clone()),
+                enclosure: link_with_rel("enclosure").map(|l| FeedEnclosure {
+                    url: Some(l.href.clone()),
+                    length: l.length.clone(),
+                    mime_type: l.mime_type.clone(),
+                }),
+                source: entry.source.as_ref().map(|s| s.title.value.clone()),
+                itunes_duration: extension(&entry, "itunes", "duration"),
+                dc_creator: extension(&entry, "dc", "creator"),
+                feed: None,
+            }
+        })
+        .collect();
+
+    Ok((info, items))
+}
+
+fn parse_json(text: &str) -> Result<(FeedChannel, Vec<FeedItem>), String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    let Some(items) = value.get("items").and_then(|v| v.as_array()) else {
+        return Err("missing \"items\" array".to_string());
+    };
+
+    let field = |item: &serde_json::Value, keys: &[&str]| -> Option<String> {
+        keys.iter().find_map(|k| match item.get(*k)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+    };
+
+    let info = FeedChannel {
+        title: field(&value, &["title"]),
+        link: field(&value, &["home_page_url", "feed_url"]),
+        description: field(&value, &["description"]),
+        last_build_date: None,
+        language: field(&value, &["language"]),
+        ttl: None,
+        image: field(&value, &["icon", "favicon"]),
+    };
+
+    let items = items
+        .iter()
+        .map(|item| {
+            // Version 1.1 uses an "authors" array, version 1.0 a single "author"
+            let author = item
+                .get("authors")
+                .and_then(|a| a.get(0))
+                .or(item.get("author"));
+            let attachment = item.get("attachments").and_then(|a| a.get(0));
+
+            FeedItem {
+                date: field(item, &["date_published", "date_modified"]),
+                title: field(item, &["title"]),
+                description: field(item, &["content_html", "content_text", "summary"]),
+                link: field(item, &["url", "external_url"]),
+                author: author.and_then(|a| field(a, &["name"])),
+                categories: item
+                    .get("tags")
+                    .and_then(|t| t.as_array())
+                    .map(|t| {
+                        t.iter()
+                            .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                guid: field(item, &["id"]),
+                comments: None,
+                enclosure: attachment.map(|a| FeedEnclosure {
+                    url: field(a, &["url"]),
+                    length: field(a, &["size_in_bytes"]),
+                    mime_type: field(a, &["mime_type"]),
+                }),
+                // JSON Feed has no source, and its "external_url" is the
+                // link of items without a "url"
+                source: None,
+                itunes_duration: attachment.and_then(|a| field(a, &["duration_in_seconds"])),
+                dc_creator: None,
+                feed: None,
+            }
+        })
+        .collect();
+
+    Ok((info, items))
+}
+
+pub fn parse_feed(text: &str) -> Result<Feed, String> {
+    let Some(kind) = detect_feed_kind(text) else {
+        return Err(
+            "unrecognised feed format (expected RSS 2.0, RSS 1.0 (RDF), Atom 1.0 or JSON Feed)"
+                .to_string(),
+        );
+    };
+
+    let mut text = text.trim_start_matches('\u{feff}').trim_start();
+
+    // The text has already been decoded, so drop any XML declaration rather
+    // than have the parsers decode it again from its "encoding" attribute
+    if text.starts_with("<?xml") {
+        if let Some(end) = text.find("?>") {
+            text = &text[end + 2..];
+        }
+    }
+
+    let (channel, items) = match kind {
+        FeedKind::Rss2 | FeedKind::Rss1 => parse_rss(text),
+        FeedKind::Atom => parse_atom(text),
+        FeedKind::Json => parse_json(text),
+    }
+    .map_err(|e| format!("detected {}, but failed to parse: {}", kind, e))?;
+
+    Ok(Feed {
+        kind,
+        channel,
+        items,
+    })
+}
+
+/// Parses the date formats found in feeds: RFC 2822 (RSS), RFC 3339 (Atom,
+/// JSON Feed and Dublin Core) and plain `YYYY-MM-DD` dates.
+pub fn parse_feed_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+
+    DateTime::parse_from_rfc2822(value)
+        .or_else(|_| DateTime::parse_from_rfc3339(value))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc().fixed_offset())
+        })
+}
+
+/// Combines the items of several feeds, newest first, dropping items whose
+/// GUID (or link, if there is no GUID) has already been seen.
+pub fn merge_feed_items(feeds: &[Feed]) -> Vec<FeedItem> {
+    let mut seen = HashSet::new();
+
+    let mut items: Vec<FeedItem> = feeds
+        .iter()
+        .enumerate()
+        .flat_map(|(i, feed)| {
+            let name = feed
+                .channel
+                .title
+                .clone()
+                .filter(|t| !t.trim().is_empty())
+                .or(feed.channel.link.clone())
+                .unwrap_or(format!("Feed {}", i + 1));
+
+            feed.items.iter().map(move |item| FeedItem {
+                feed: Some(name.clone()),
+                ..item.clone()
+            })
+        })
+        .filter(|item| match item.guid.as_ref().or(item.link.as_ref()) {
+            Some(key) => seen.insert(key.clone()),
+            None => true,
+        })
+        .collect();
+
+    // Stable, so undated items keep their feed order at the end
+    items.sort_by_key(|item| std::cmp::Reverse(item.date.as_deref().and_then(parse_feed_date)));
+
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, date: Option<&str>, guid: Option<&str>, link: Option<&str>) -> FeedItem {
+        FeedItem {
+            title: Some(title.to_string()),
+            date: date.map(str::to_string),
+            guid: guid.map(str::to_string),
+            link: link.map(str::to_string),
+            ..FeedItem::default()
+        }
+    }
+
+    fn feed(title: Option<&str>, items: Vec<FeedItem>) -> Feed {
+        Feed {
+            kind: FeedKind::Rss2,
+            channel: FeedChannel {
+                title: title.map(str::to_string),
+                ..FeedChannel::default()
+            },
+            items,
+        }
+    }
+
+    fn titles(items: &[FeedItem]) -> Vec<&str> {
+        items.iter().filter_map(|i| i.title.as_deref()).collect()
+    }
+
+    #[test]
+    fn merges_newest_first() {
+        let items = merge_feed_items(&[
+            feed(
+                Some("One"),
+                vec![
+                    item("a", Some("Mon, 01 Jan 2024 10:00:00 +0000"), None, None),
+                    item("undated 1", None, None, None),
+                    item("c", Some("2024-01-03"), None, None),
+                ],
+            ),
+            feed(
+                None,
+                vec![
+                    item("undated 2", None, None, None),
+                    item("b", Some("2024-01-02T00:00:00+01:00"), None, None),
+                ],
+            ),
+        ]);
+
+        assert_eq!(titles(&items), ["c", "b", "a", "undated 1", "undated 2"]);
+        let feeds: Vec<_> = items.iter().filter_map(|i| i.feed.as_deref()).collect();
+        assert_eq!(feeds, ["One", "Feed 2", "One", "One", "Feed 2"]);
+    }
+
+    #[test]
+    fn merge_drops_repeated_items() {
+        let items = merge_feed_items(&[
+            feed(
+                Some("One"),
+                vec![
+                    item("a", None, Some("id-1"), Some("https://a")),
+                    item("b", None, None, Some("https://b")),
+                    item("c", None, None, None),
+                ],
+            ),
+            feed(
+                Some("Two"),
+                vec![
+                    // Same GUID, different link
+                    item("a again", None, Some("id-1"), Some("https://other")),
+                    // Same link, no GUID
+                    item("b again", None, None, Some("https://b")),
+                    // A GUID is the key even if the link was seen
+                    item("d", None, Some("id-2"), Some("https://a")),
+                    item("c", None, None, None),
+                ],
+            ),
+        ]);
+
+        assert_eq!(titles(&items), ["a", "b", "c", "d", "c"]);
+    }
+
+    #[test]
+    fn feed_kinds() {
+        assert_eq!(
+            detect_feed_kind("\u{feff}  {\"version\": 1}"),
+            Some(FeedKind::Json)
+        );
+        assert_eq!(
+            detect_feed_kind("<?xml version=\"1.0\"?><!-- x --><rss version=\"2.0\">"),
+            Some(FeedKind::Rss2)
+        );
+        assert_eq!(
+            detect_feed_kind("<rdf:RDF xmlns:rdf=\"x\">"),
+            Some(FeedKind::Rss1)
+        );
+        assert_eq!(detect_feed_kind("<feed xmlns=\"x\">"), Some(FeedKind::Atom));
+        assert_eq!(detect_feed_kind("<html>"), None);
+    }
+
+    #[test]
+    fn description_modes() {
+        assert_eq!(
+            DescriptionMode::from_name(" Text "),
+            Some(DescriptionMode::Text)
+        );
+        assert_eq!(
+            DescriptionMode::from_name("truncate:80"),
+            Some(DescriptionMode::Truncate(80))
+        );
+        assert_eq!(DescriptionMode::from_name("truncate:0"), None);
+        assert_eq!(DescriptionMode::from_name("truncate:"), None);
+        assert_eq!(DescriptionMode::from_name("html"), None);
+    }
+}
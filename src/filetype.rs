@@ -0,0 +1,142 @@
+use std::{collections::HashMap, fs, fs::File, io::Read, path::Path};
+
+/// Categories of common file extensions, before any from a type map file.
+const DEFAULT_CATEGORIES: [(&str, &[&str]); 10] = [
+    ("Text", &["txt", "log", "csv", "tsv"]),
+    (
+        "Image",
+        &[
+            "png", "jpg", "jpeg", "bmp", "gif", "webp", "tif", "tiff", "heic", "svg", "ico",
+        ],
+    ),
+    ("Video", &["mp4", "m4v", "mov", "mkv", "avi", "webm", "wmv"]),
+    (
+        "Audio",
+        &["mp3", "wav", "aiff", "ogg", "flac", "m4a", "opus"],
+    ),
+    (
+        "Document",
+        &["md", "pdf", "rtf", "doc", "docx", "odt", "fodt", "epub"],
+    ),
+    ("Slideshow", &["ppt", "pptx", "fodp", "odp", "key"]),
+    ("Spreadsheet", &["xls", "xlsx", "ods", "fods", "numbers"]),
+    (
+        "Code",
+        &[
+            "c", "h", "cpp", "hpp", "rs", "js", "ts", "py", "xml", "html", "css", "php", "sh",
+            "cmd", "bat", "java", "go", "rb", "json", "toml", "yaml", "yml",
+        ],
+    ),
+    (
+        "Archive",
+        &["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar"],
+    ),
+    ("Font", &["ttf", "otf", "woff", "woff2"]),
+];
+
+/// Categories of files (e.g. "Image"), by lowercase extension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeMap {
+    categories: HashMap<String, String>,
+}
+
+impl Default for TypeMap {
+    fn default() -> TypeMap {
+        TypeMap {
+            categories: DEFAULT_CATEGORIES
+                .iter()
+                .flat_map(|(category, extensions)| {
+                    extensions
+                        .iter()
+                        .map(|e| (e.to_string(), category.to_string()))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TypeMap {
+    /// Adds the categories of a type map file to the defaults. Each line is
+    /// `CATEGORY: EXTENSION...`, with extensions separated by commas or
+    /// spaces, e.g. `Image: png, jpg`. Lines starting with `#` are ignored.
+    pub fn parse(text: &str) -> Result<TypeMap, String> {
+        let mut map = TypeMap::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((category, extensions)) = line.split_once(':') else {
+                return Err(format!(
+                    "line {}: expected CATEGORY: EXTENSION..., got \"{}\"",
+                    number + 1,
+                    line
+                ));
+            };
+
+            for ext in extensions
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(|e| e.trim_start_matches('.'))
+                .filter(|e| !e.is_empty())
+            {
+                map.categories
+                    .insert(ext.to_lowercase(), category.trim().to_string());
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Reads a type map file, as [`TypeMap::parse`] does.
+    pub fn load(path: &str) -> Result<TypeMap, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        TypeMap::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Category of a file by its extension, ignoring case.
+    pub fn category(&self, path: &Path) -> Option<&str> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        self.categories.get(&ext).map(|c| c.as_str())
+    }
+}
+
+/// MIME type of a file sniffed from its first bytes, if they are recognised.
+pub fn sniff_mime(path: &Path) -> Option<String> {
+    let mut head = Vec::new();
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(8192).read_to_end(&mut head);
+    }
+
+    infer::get(&head).map(|t| t.mime_type().to_string())
+}
+
+/// MIME type of a file guessed from its extension.
+pub fn guess_mime(path: &Path) -> Option<String> {
+    mime_guess::from_path(path)
+        .first_raw()
+        .map(|m| m.to_string())
+}
+
+/// Category of a MIME type, for files whose extension has none.
+pub fn mime_category(mime: &str) -> Option<&'static str> {
+    match mime.split('/').next()? {
+        "text" => Some("Text"),
+        "image" => Some("Image"),
+        "video" => Some("Video"),
+        "audio" => Some("Audio"),
+        "font" => Some("Font"),
+        _ => match mime {
+            "application/pdf" | "application/rtf" | "application/epub+zip" => Some("Document"),
+            "application/zip"
+            | "application/x-tar"
+            | "application/gzip"
+            | "application/x-bzip2"
+            | "application/x-xz"
+            | "application/x-7z-compressed"
+            | "application/vnd.rar" => Some("Archive"),
+            _ => None,
+        },
+    }
+}
@@ -0,0 +1,166 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// A content hash of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+    Crc32,
+}
+
+impl HashAlgorithm {
+    pub const fn heading(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Blake3 => "BLAKE3",
+            HashAlgorithm::Crc32 => "CRC32",
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Hasher {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+            HashAlgorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    /// Lowercase hexadecimal digest.
+    fn finish(self) -> String {
+        match self {
+            Hasher::Sha256(h) => h.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Crc32(h) => format!("{:08x}", h.finalize()),
+        }
+    }
+}
+
+/// Hashes a file's contents with each of the algorithms, reading it once.
+pub fn hash_file(path: &Path, algorithms: &[HashAlgorithm]) -> Result<Vec<String>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    hash_reader(file, algorithms)
+}
+
+/// Hashes everything read from a reader with each of the algorithms.
+pub fn hash_reader(reader: impl Read, algorithms: &[HashAlgorithm]) -> Result<Vec<String>, String> {
+    HashingReader::new(reader, algorithms).finish()
+}
+
+/// Reads from another reader, hashing what is read with each of the
+/// algorithms, so that contents can be hashed as they are read for another
+/// purpose.
+pub struct HashingReader<R> {
+    reader: R,
+    hashers: Vec<Hasher>,
+    /// First error reading, other than an interruption.
+    error: Option<String>,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(reader: R, algorithms: &[HashAlgorithm]) -> HashingReader<R> {
+        HashingReader {
+            reader,
+            hashers: algorithms.iter().map(|a| Hasher::new(*a)).collect(),
+            error: None,
+        }
+    }
+
+    /// Reads whatever is left to hash, then gives the digests of everything
+    /// read, or the first error reading.
+    pub fn finish(mut self) -> Result<Vec<String>, String> {
+        if !self.hashers.is_empty() && self.error.is_none() {
+            // The error is kept by `read`
+            let _ = io::copy(&mut self, &mut io::sink());
+        }
+
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.hashers.into_iter().map(|h| h.finish()).collect()),
+        }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self.reader.read(buffer) {
+            Ok(n) => {
+                for h in self.hashers.iter_mut() {
+                    h.update(&buffer[..n]);
+                }
+                Ok(n)
+            }
+            Err(e) => {
+                if e.kind() != io::ErrorKind::Interrupted && self.error.is_none() {
+                    self.error = Some(e.to_string());
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Hashes files as [`hash_file`] does, on as many threads as there are
+/// processors, giving the results in the order of the paths, or an error if
+/// a thread fails.
+pub fn hash_files(
+    paths: &[&Path],
+    algorithms: &[HashAlgorithm],
+) -> Result<Vec<Result<Vec<String>, String>>, String> {
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(paths.len());
+    let next = AtomicUsize::new(0);
+
+    let results: Result<Vec<_>, String> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            return results;
+                        };
+                        results.push((i, hash_file(path, algorithms)));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|w| w.join().map_err(|_| "hashing failed".to_string()))
+            .collect()
+    });
+
+    let mut results: Vec<(usize, Result<Vec<String>, String>)> =
+        results?.into_iter().flatten().collect();
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().map(|(_, r)| r).collect())
+}
@@ -0,0 +1,328 @@
+/// Decodes named and numeric character references, leaving unknown ones as-is.
+pub fn decode_entities(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let name = &rest[1..end];
+            let c = match name.strip_prefix('#') {
+                Some(n) => match n.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => n.parse::<u32>().ok(),
+                }
+                .and_then(char::from_u32)
+                .map(|c| c.to_string()),
+                None => named_entity(name).map(|s| s.to_string()),
+            };
+            c.map(|c| (c, end + 1))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                out.push_str(&c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn named_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        "nbsp" => " ",
+        "shy" => "",
+        "ndash" => "–",
+        "mdash" => "—",
+        "hellip" => "…",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "sbquo" => "‚",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "bdquo" => "„",
+        "laquo" => "«",
+        "raquo" => "»",
+        "bull" => "•",
+        "middot" => "·",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "deg" => "°",
+        "times" => "×",
+        "divide" => "÷",
+        "euro" => "€",
+        "pound" => "£",
+        "yen" => "¥",
+        "cent" => "¢",
+        "sect" => "§",
+        "para" => "¶",
+        "auml" => "ä",
+        "ouml" => "ö",
+        "uuml" => "ü",
+        "Auml" => "Ä",
+        "Ouml" => "Ö",
+        "Uuml" => "Ü",
+        "szlig" => "ß",
+        "eacute" => "é",
+        "egrave" => "è",
+        "ecirc" => "ê",
+        "Eacute" => "É",
+        "aacute" => "á",
+        "agrave" => "à",
+        "acirc" => "â",
+        "ccedil" => "ç",
+        "iacute" => "í",
+        "oacute" => "ó",
+        "uacute" => "ú",
+        "ntilde" => "ñ",
+        _ => return None,
+    })
+}
+
+/// A tag found in an HTML fragment, e.g. `<a href="...">` or `</p>`.
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    body: &'a str,
+}
+
+/// Finds where the next tag or comment starts: a `<` followed by a letter,
+/// `/` or `!`. Others, as in "a < b", are text.
+fn find_tag(html: &str) -> Option<usize> {
+    html.match_indices('<').map(|(i, _)| i).find(|i| {
+        html[i + 1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!')
+    })
+}
+
+/// Splits the tag starting at the beginning of `html` (which must begin with
+/// `<`), returning it and the remaining input.
+fn next_tag(html: &str) -> (Option<Tag<'_>>, &str) {
+    if let Some(comment) = html.strip_prefix("<!--") {
+        return match comment.find("-->") {
+            Some(end) => (None, &comment[end + 3..]),
+            None => (None, ""),
+        };
+    }
+
+    let mut quote = None;
+    let mut end = html.len();
+
+    for (i, c) in html.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let body = &html[1..end];
+    let rest = html.get(end + 1..).unwrap_or("");
+    let closing = body.starts_with('/');
+    let name = body
+        .trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    (
+        Some(Tag {
+            name,
+            closing,
+            body,
+        }),
+        rest,
+    )
+}
+
+/// Returns the value of an attribute within a tag body, matching the
+/// (lowercase) name case-insensitively.
+pub(crate) fn tag_attribute(body: &str, name: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let mut from = 0;
+
+    while let Some(i) = lower[from..].find(name) {
+        let start = from + i;
+        from = start + name.len();
+
+        let preceded_by_space = lower[..start].ends_with(|c: char| c.is_whitespace());
+        let after = body[from..].trim_start();
+
+        if !preceded_by_space || !after.starts_with('=') {
+            continue;
+        }
+
+        let value = after[1..].trim_start();
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or(""),
+            _ => value
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or(""),
+        };
+
+        return Some(decode_entities(value));
+    }
+
+    None
+}
+
+/// Converts an HTML fragment into readable plain text: tags are removed,
+/// entities decoded, whitespace collapsed, and block elements become line or
+/// paragraph breaks.
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(start) = find_tag(rest) else {
+            out.push_str(&rest.replace(char::is_whitespace, " "));
+            break;
+        };
+
+        out.push_str(&rest[..start].replace(char::is_whitespace, " "));
+
+        let (tag, after) = next_tag(&rest[start..]);
+        rest = after;
+
+        let Some(tag) = tag else {
+            continue;
+        };
+
+        match tag.name.as_str() {
+            "script" | "style" if !tag.closing => {
+                let close = format!("</{}", tag.name);
+                rest = match rest.to_ascii_lowercase().find(&close) {
+                    Some(i) => &rest[i..],
+                    None => "",
+                };
+            }
+            "br" => out.push('\n'),
+            "li" if !tag.closing => out.push_str("\n• "),
+            "p" | "div" | "blockquote" | "pre" | "ul" | "ol" | "table" | "h1" | "h2" | "h3"
+            | "h4" | "h5" | "h6" | "hr" => out.push_str("\n\n"),
+            "tr" => out.push('\n'),
+            "td" | "th" if tag.closing => out.push('\t'),
+            _ => {}
+        }
+    }
+
+    let text = decode_entities(&out);
+
+    // Collapse runs of spaces and tidy up line breaks
+    let mut lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            line.split(' ')
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .map(|line| line.trim_matches('\t').to_string())
+        .collect();
+
+    lines.dedup_by(|a, b| a.is_empty() && b.is_empty());
+
+    lines.join("\n").trim().to_string()
+}
+
+/// Returns the target of the first `<a href="...">` in an HTML fragment.
+pub fn first_link(html: &str) -> Option<String> {
+    let mut rest = html;
+
+    while let Some(start) = find_tag(rest) {
+        let (tag, after) = next_tag(&rest[start..]);
+        rest = after;
+
+        if let Some(tag) = tag {
+            if tag.name == "a" && !tag.closing {
+                if let Some(href) = tag_attribute(tag.body, "href") {
+                    return Some(href);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Cuts text longer than `length` characters down to that length, marking the
+/// cut with an ellipsis counted in the length.
+pub fn truncate_text(text: &str, length: usize) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(length.saturating_sub(1)).collect();
+
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_of_html() {
+        assert_eq!(
+            html_to_text("<p>One &amp; <b>two</b></p><p>Three<br>four</p>"),
+            "One & two\n\nThree\nfour"
+        );
+        assert_eq!(
+            html_to_text("<ul><li>a</li><li>b</li></ul><!-- note -->"),
+            "• a\n• b"
+        );
+        assert_eq!(
+            html_to_text("<script>x = '<p>'</script>Shown<style>p {}</style>"),
+            "Shown"
+        );
+    }
+
+    #[test]
+    fn less_than_is_text() {
+        assert_eq!(html_to_text("a < b and c <= d"), "a < b and c <= d");
+        assert_eq!(html_to_text("1 <2 <b>bold</b>"), "1 <2 bold");
+        assert_eq!(html_to_text("x <"), "x <");
+        assert_eq!(
+            first_link("5 < 6 <a href=\"https://example.com\">link</a>"),
+            Some("https://example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn first_link_of_html() {
+        assert_eq!(
+            first_link("<p><a name=x>no</a> <A HREF='/one'>1</A> <a href=/two>2</a>"),
+            Some("/one".to_string())
+        );
+        assert_eq!(first_link("<p>none</p>"), None);
+    }
+
+    #[test]
+    fn truncate_to_length() {
+        assert_eq!(truncate_text("Hello world", 11), "Hello world");
+        assert_eq!(truncate_text("Hello world", 5), "Hell…");
+        assert_eq!(truncate_text("Hello world", 7), "Hello…");
+        assert_eq!(truncate_text("Ünïcödé", 3), "Ün…");
+        assert_eq!(truncate_text("abc", 1), "…");
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+use crate::{
+    cell::Cell,
+    locale::{LocaleNumber, NumberLocale},
+};
+
+/// Number of non-empty values looked at when inferring a column's type.
+const INFERENCE_SAMPLE: usize = 1000;
+
+/// More significant digits than a spreadsheet number can hold exactly, so
+/// longer digit strings (card numbers, long IDs) are kept as text.
+const MAX_DIGITS: usize = 15;
+
+/// Date formats tried when a date column has no explicit format.
+const DATE_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnType {
+    Text,
+    Number,
+    Integer,
+    Boolean,
+    /// A date, read with the given `chrono` format or common ISO forms.
+    Date(Option<String>),
+    Percent,
+    Currency,
+}
+
+impl ColumnType {
+    /// Parses `text`, `number`, `integer`, `boolean`, `date`, `date:FORMAT`,
+    /// `percent` or `currency`.
+    pub fn from_name(name: &str) -> Option<ColumnType> {
+        // The format keeps its case, as `%Y` and `%y` differ
+        if let Some((kind, format)) = name.trim().split_once(':') {
+            return kind
+                .eq_ignore_ascii_case("date")
+                .then(|| ColumnType::Date(Some(format.to_string())));
+        }
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(ColumnType::Text),
+            "number" => Some(ColumnType::Number),
+            "integer" => Some(ColumnType::Integer),
+            "boolean" => Some(ColumnType::Boolean),
+            "date" => Some(ColumnType::Date(None)),
+            "percent" => Some(ColumnType::Percent),
+            "currency" => Some(ColumnType::Currency),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            ColumnType::Text => "text".to_string(),
+            ColumnType::Number => "number".to_string(),
+            ColumnType::Integer => "integer".to_string(),
+            ColumnType::Boolean => "boolean".to_string(),
+            ColumnType::Date(None) => "date".to_string(),
+            ColumnType::Date(Some(f)) => format!("date:{}", f),
+            ColumnType::Percent => "percent".to_string(),
+            ColumnType::Currency => "currency".to_string(),
+        }
+    }
+
+    /// Parses `COLUMN=TYPE`, where the column is a heading or 1-based index.
+    pub fn parse_override(spec: &str) -> Result<(String, ColumnType), String> {
+        let Some((column, name)) = spec.rsplit_once('=') else {
+            return Err(format!("expected COLUMN=TYPE, got \"{}\"", spec));
+        };
+
+        match ColumnType::from_name(name) {
+            Some(t) => Ok((column.trim().to_string(), t)),
+            None => Err(format!("unknown column type \"{}\"", name)),
+        }
+    }
+
+    /// Converts a value to a cell of this type, or `None` if it doesn't fit.
+    /// Numbers are read with the locale's separators.
+    pub fn parse(&self, value: &str, locale: &NumberLocale) -> Option<Cell> {
+        if *self == ColumnType::Text {
+            return Some(Cell::Text(value.to_string()));
+        }
+
+        let value = value.trim();
+
+        match self {
+            ColumnType::Text => unreachable!(),
+            ColumnType::Boolean => parse_boolean(value).map(Cell::Boolean),
+            ColumnType::Date(format) => parse_date(value, format.as_deref()).map(Cell::DateTime),
+            ColumnType::Number
+            | ColumnType::Integer
+            | ColumnType::Percent
+            | ColumnType::Currency => {
+                let n = locale.parse(value)?;
+                let fits = match self {
+                    ColumnType::Number => n.currency.is_none() && !n.percent,
+                    // Exponents can write fractions without decimals, e.g. 1e-5
+                    ColumnType::Integer => {
+                        n.currency.is_none()
+                            && !n.percent
+                            && n.decimals == 0
+                            && n.value.fract() == 0.0
+                    }
+                    ColumnType::Percent => n.currency.is_none() && n.percent,
+                    _ => !n.percent,
+                };
+
+                match (fits, self) {
+                    (false, _) => None,
+                    (true, ColumnType::Currency) if n.currency.is_none() => Some(
+                        LocaleNumber {
+                            currency: Some(String::new()),
+                            ..n
+                        }
+                        .to_cell(),
+                    ),
+                    (true, _) => Some(n.to_cell()),
+                }
+            }
+        }
+    }
+
+    /// Converts a value to a cell of this type, falling back to text. Empty
+    /// values in non-text columns become empty cells.
+    pub fn to_cell(&self, value: &str, locale: &NumberLocale) -> Cell {
+        if value.trim().is_empty() && *self != ColumnType::Text {
+            return Cell::Empty;
+        }
+
+        self.parse(value, locale)
+            .unwrap_or_else(|| Cell::Text(value.to_string()))
+    }
+}
+
+/// Checks that a string is a plain decimal number: an optional sign, digits
+/// without a leading zero, an optional fraction and exponent. This rules out
+/// "NaN", "inf", hexadecimal and zero-padded codes like "00123".
+fn is_plain_number(value: &str) -> bool {
+    let unsigned = value.strip_prefix(['-', '+']).unwrap_or(value);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((m, e)) => (m, Some(e.strip_prefix(['-', '+']).unwrap_or(e))),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    (digits(integer) || fraction.is_some_and(digits))
+        && (integer.is_empty() || digits(integer))
+        && fraction.is_none_or(|f| f.is_empty() || digits(f))
+        && exponent.is_none_or(digits)
+        && !(integer.len() > 1 && integer.starts_with('0'))
+        && integer.len() + fraction.map_or(0, |f| f.len()) <= MAX_DIGITS
+}
+
+pub fn parse_number(value: &str) -> Option<f64> {
+    match is_plain_number(value) {
+        true => value.parse::<f64>().ok(),
+        false => None,
+    }
+}
+
+pub fn parse_boolean(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn parse_date(value: &str, format: Option<&str>) -> Option<NaiveDateTime> {
+    let with_format = |f: &str| {
+        NaiveDateTime::parse_from_str(value, f).ok().or_else(|| {
+            NaiveDate::parse_from_str(value, f)
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+    };
+
+    match format {
+        Some(f) => with_format(f),
+        None => with_format("%Y-%m-%d")
+            .or_else(|| DATE_FORMATS.into_iter().find_map(with_format))
+            .or_else(|| {
+                DateTime::parse_from_rfc3339(value)
+                    .or_else(|_| DateTime::parse_from_rfc2822(value))
+                    .ok()
+                    .map(|d| d.naive_local())
+            }),
+    }
+}
+
+/// Picks the narrowest type that every sampled non-empty value fits, or text
+/// if there is none.
+pub fn infer_column_type<'a>(
+    values: impl Iterator<Item = &'a str>,
+    locale: &NumberLocale,
+) -> ColumnType {
+    let sample: Vec<&str> = values
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .take(INFERENCE_SAMPLE)
+        .collect();
+
+    if sample.is_empty() {
+        return ColumnType::Text;
+    }
+
+    [
+        ColumnType::Boolean,
+        ColumnType::Integer,
+        ColumnType::Number,
+        ColumnType::Percent,
+        ColumnType::Currency,
+        ColumnType::Date(None),
+    ]
+    .into_iter()
+    .find(|t| sample.iter().all(|v| t.parse(v, locale).is_some()))
+    .unwrap_or(ColumnType::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(values: &[&str]) -> ColumnType {
+        infer_column_type(values.iter().copied(), &NumberLocale::default())
+    }
+
+    #[test]
+    fn exponents_are_integers_only_when_whole() {
+        assert_eq!(infer(&["1e-5", "3E-7"]), ColumnType::Number);
+        assert_eq!(infer(&["1", "1.5e3"]), ColumnType::Number);
+        assert_eq!(infer(&["1e3", "2"]), ColumnType::Integer);
+        assert!(ColumnType::Integer
+            .parse("1e-5", &NumberLocale::default())
+            .is_none());
+    }
+
+    #[test]
+    fn narrowest_type() {
+        assert_eq!(infer(&["yes", "No", "TRUE"]), ColumnType::Boolean);
+        assert_eq!(infer(&["1", " 2 ", "", "-3"]), ColumnType::Integer);
+        assert_eq!(infer(&["1", "2.5"]), ColumnType::Number);
+        assert_eq!(infer(&["10%", "2.5%"]), ColumnType::Percent);
+        assert_eq!(infer(&["$1", "2"]), ColumnType::Currency);
+        assert_eq!(
+            infer(&["2024-01-31", "2024-02-01 10:30"]),
+            ColumnType::Date(None)
+        );
+        assert_eq!(infer(&["1", "a"]), ColumnType::Text);
+        assert_eq!(infer(&["", " "]), ColumnType::Text);
+    }
+
+    #[test]
+    fn codes_stay_text() {
+        assert_eq!(infer(&["00123", "00456"]), ColumnType::Text);
+        assert_eq!(infer(&["4111111111111111"]), ColumnType::Text);
+        assert_eq!(infer(&["NaN", "inf"]), ColumnType::Text);
+        assert_eq!(infer(&["0x1F"]), ColumnType::Text);
+        assert_eq!(infer(&["0", "0.5", ".5"]), ColumnType::Number);
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(
+            ColumnType::from_name(" Integer "),
+            Some(ColumnType::Integer)
+        );
+        assert_eq!(
+            ColumnType::from_name("DATE:%d/%m/%Y"),
+            Some(ColumnType::Date(Some("%d/%m/%Y".to_string())))
+        );
+        assert_eq!(ColumnType::from_name("float"), None);
+        assert_eq!(
+            ColumnType::parse_override("a=b=number"),
+            Ok(("a=b".to_string(), ColumnType::Number))
+        );
+        assert!(ColumnType::parse_override("amount").is_err());
+        assert!(ColumnType::parse_override("amount=float").is_err());
+
+        let date = ColumnType::Date(Some("%d/%m/%Y".to_string()));
+        assert_eq!(ColumnType::from_name(&date.name()), Some(date));
+    }
+
+    #[test]
+    fn values_that_do_not_fit_are_text() {
+        let en = NumberLocale::default();
+        let date = ColumnType::Date(Some("%d/%m/%Y".to_string()));
+
+        assert!(matches!(date.to_cell("31/01/2024", &en), Cell::DateTime(_)));
+        assert!(matches!(date.to_cell("2024-01-31", &en), Cell::Text(_)));
+        assert!(matches!(ColumnType::Number.to_cell(" ", &en), Cell::Empty));
+        assert!(matches!(ColumnType::Text.to_cell(" ", &en), Cell::Text(_)));
+        assert!(ColumnType::Number.parse("5%", &en).is_none());
+        assert!(ColumnType::Percent.parse("5", &en).is_none());
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+
+use crate::{cell::Cell, row::Row, table::Table};
+
+/// Which rows a join keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    /// Rows matched on both sides.
+    Inner,
+    /// Every left row, matched or not.
+    Left,
+    /// Every row of both sides, matched or not.
+    Full,
+}
+
+impl JoinKind {
+    pub const ALL: [JoinKind; 3] = [JoinKind::Inner, JoinKind::Left, JoinKind::Full];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "inner",
+            JoinKind::Left => "left",
+            JoinKind::Full => "full",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<JoinKind> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "outer" => Some(JoinKind::Full),
+            name => JoinKind::ALL.into_iter().find(|k| k.name() == name),
+        }
+    }
+}
+
+/// A pair of columns whose values must be equal for rows to match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinKey {
+    /// Heading or 1-based index of the column in the left table.
+    pub left: String,
+    /// Heading or 1-based index of the column in the right table.
+    pub right: String,
+}
+
+impl JoinKey {
+    /// Parses `COLUMN`, for a column of the same heading on both sides, or
+    /// `LEFT=RIGHT`.
+    pub fn parse(spec: &str) -> Result<JoinKey, String> {
+        let (left, right) = spec.split_once('=').unwrap_or((spec, spec));
+        let (left, right) = (left.trim(), right.trim());
+
+        if left.is_empty() || right.is_empty() {
+            return Err(format!("expected COLUMN or LEFT=RIGHT, got \"{}\"", spec));
+        }
+
+        Ok(JoinKey {
+            left: left.to_string(),
+            right: right.to_string(),
+        })
+    }
+
+    /// Formats the key the way [`JoinKey::parse`] reads it.
+    pub fn name(&self) -> String {
+        match self.left == self.right {
+            true => self.left.clone(),
+            false => format!("{}={}", self.left, self.right),
+        }
+    }
+}
+
+/// How two tables are joined into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub keys: Vec<JoinKey>,
+    /// Added to the headings of left and right columns that clash.
+    pub suffixes: (String, String),
+}
+
+impl Default for Join {
+    fn default() -> Join {
+        Join {
+            kind: JoinKind::Inner,
+            keys: Vec::new(),
+            suffixes: ("_left".to_string(), "_right".to_string()),
+        }
+    }
+}
+
+impl Join {
+    /// Parses `[KIND:]KEYS`, where the keys are comma-separated.
+    pub fn parse(spec: &str) -> Result<Join, String> {
+        let mut join = Join::default();
+
+        let keys = match spec.split_once(':') {
+            Some((kind, keys)) => match JoinKind::from_name(kind) {
+                Some(k) => {
+                    join.kind = k;
+                    keys
+                }
+                None => spec,
+            },
+            None => spec,
+        };
+
+        join.keys = keys
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(JoinKey::parse)
+            .collect::<Result<Vec<JoinKey>, String>>()?;
+
+        if join.keys.is_empty() {
+            return Err(format!("expected [KIND:]KEYS, got \"{}\"", spec));
+        }
+
+        Ok(join)
+    }
+
+    /// Formats the join the way [`Join::parse`] reads it.
+    pub fn name(&self) -> String {
+        format!(
+            "{}:{}",
+            self.kind.name(),
+            self.keys
+                .iter()
+                .map(|k| k.name())
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+
+    /// Reads `LEFT,RIGHT` into the suffixes.
+    pub fn set_suffixes(&mut self, list: &str) -> Result<(), String> {
+        match list.split_once(',') {
+            Some((left, right)) if left != right => {
+                self.suffixes = (left.to_string(), right.to_string());
+                Ok(())
+            }
+            _ => Err(format!(
+                "expected two different suffixes LEFT,RIGHT, got \"{}\"",
+                list
+            )),
+        }
+    }
+}
+
+/// Text a key cell is matched by, or `None` if it is empty.
+fn key_text(cell: Option<&Cell>) -> Option<String> {
+    let text = match cell? {
+        Cell::Formula(_, v) => v.to_string(),
+        c => c.to_string(),
+    };
+
+    Some(text.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// Cells of a row at the given columns, empty where it has none.
+fn cells_at(row: Option<&Row>, columns: &[usize]) -> Vec<Cell> {
+    columns
+        .iter()
+        .map(|i| {
+            row.and_then(|r| r.cells.get(*i))
+                .cloned()
+                .unwrap_or(Cell::Empty)
+        })
+        .collect()
+}
+
+impl Table {
+    /// Joins the rows of another table to this one's, matching them on the
+    /// join's keys. The result has this table's columns, then the other's
+    /// except its keys.
+    pub fn join(self, other: Table, join: &Join) -> Result<Table, String> {
+        let column = |table: &Table, c: &String, side: &str| {
+            table
+                .column_index(c)
+                .ok_or(format!("unknown {} column \"{}\"", side, c))
+        };
+
+        let mut left_keys = Vec::new();
+        let mut right_keys = Vec::new();
+        for k in &join.keys {
+            left_keys.push(column(&self, &k.left, "left")?);
+            right_keys.push(column(&other, &k.right, "right")?);
+        }
+
+        let left_width = self.width();
+        let right_columns: Vec<usize> = (0..other.width())
+            .filter(|i| !right_keys.contains(i))
+            .collect();
+
+        let headings = match (&self.headings, &other.headings) {
+            (None, None) => None,
+            (left, right) => {
+                let heading = |h: &Option<Vec<String>>, i: usize| {
+                    h.as_ref()
+                        .and_then(|h| h.get(i).cloned())
+                        .unwrap_or_default()
+                };
+                let left: Vec<String> = (0..left_width).map(|i| heading(left, i)).collect();
+                let right: Vec<String> = right_columns.iter().map(|i| heading(right, *i)).collect();
+                let clashes = |h: &String, others: &[String]| {
+                    !h.is_empty() && others.iter().any(|o| o.eq_ignore_ascii_case(h))
+                };
+
+                Some(
+                    left.iter()
+                        .map(|h| match clashes(h, &right) {
+                            true => format!("{}{}", h, join.suffixes.0),
+                            false => h.clone(),
+                        })
+                        .chain(right.iter().map(|h| match clashes(h, &left) {
+                            true => format!("{}{}", h, join.suffixes.1),
+                            false => h.clone(),
+                        }))
+                        .collect(),
+                )
+            }
+        };
+
+        let key = |row: &Row, keys: &[usize]| {
+            keys.iter()
+                .map(|i| key_text(row.cells.get(*i)))
+                .collect::<Option<Vec<String>>>()
+        };
+
+        // Right rows by key, skipping those with an empty key, which match
+        // nothing
+        let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+        for (i, row) in other.rows.iter().enumerate() {
+            if let Some(k) = key(row, &right_keys) {
+                index.entry(k).or_default().push(i);
+            }
+        }
+
+        let left_columns: Vec<usize> = (0..left_width).collect();
+
+        let mut matched = vec![false; other.rows.len()];
+        let mut rows = Vec::new();
+
+        for row in &self.rows {
+            let matches = key(row, &left_keys)
+                .and_then(|k| index.get(&k))
+                .map(|m| m.as_slice())
+                .unwrap_or_default();
+
+            for i in matches {
+                matched[*i] = true;
+                rows.push(Row::from_cells(
+                    [
+                        cells_at(Some(row), &left_columns),
+                        cells_at(Some(&other.rows[*i]), &right_columns),
+                    ]
+                    .concat(),
+                ));
+            }
+
+            if matches.is_empty() && join.kind != JoinKind::Inner {
+                rows.push(Row::from_cells(
+                    [
+                        cells_at(Some(row), &left_columns),
+                        cells_at(None, &right_columns),
+                    ]
+                    .concat(),
+                ));
+            }
+        }
+
+        if join.kind == JoinKind::Full {
+            // Unmatched right rows, with their keys in the left key columns
+            for (row, _) in other.rows.iter().zip(matched).filter(|(_, m)| !m) {
+                let mut cells: Vec<Cell> = vec![Cell::Empty; left_width];
+                for (l, r) in left_keys.iter().zip(&right_keys) {
+                    cells[*l] = row.cells.get(*r).cloned().unwrap_or(Cell::Empty);
+                }
+                cells.extend(cells_at(Some(row), &right_columns));
+
+                rows.push(Row::from_cells(cells));
+            }
+        }
+
+        Ok(Table {
+            preamble: self.preamble,
+            headings,
+            rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(headings: Vec<&str>, rows: &[&[&str]]) -> Table {
+        Table::new().with_headings(headings).with_rows(
+            rows.iter()
+                .map(|r| Row::from_cells(r.iter().map(|c| Cell::Text(c.to_string())).collect()))
+                .collect(),
+        )
+    }
+
+    fn text(table: &Table) -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|r| {
+                r.cells
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+            .collect()
+    }
+
+    fn join(kind: &str) -> Table {
+        let people = table(
+            vec!["Id", "Name"],
+            &[&["1", "Ann"], &["2", "Bob"], &["", "Cy"], &["3", "Di"]],
+        );
+        let orders = table(
+            vec!["Person", "Name", "Item"],
+            &[
+                &["1", "A", "pen"],
+                &["3", "D", "ink"],
+                &["1", "A", "cap"],
+                &["4", "E", "box"],
+                &["", "F", "mug"],
+            ],
+        );
+
+        people
+            .join(
+                orders,
+                &Join::parse(&format!("{}:Id=Person", kind)).unwrap(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn inner() {
+        let t = join("inner");
+
+        assert_eq!(
+            t.headings.clone().unwrap(),
+            ["Id", "Name_left", "Name_right", "Item"]
+        );
+        assert_eq!(text(&t), ["1|Ann|A|pen", "1|Ann|A|cap", "3|Di|D|ink"]);
+    }
+
+    #[test]
+    fn left_and_full() {
+        assert_eq!(
+            text(&join("left")),
+            [
+                "1|Ann|A|pen",
+                "1|Ann|A|cap",
+                "2|Bob||",
+                "|Cy||",
+                "3|Di|D|ink"
+            ]
+        );
+        // Unmatched right rows come last, with their keys on the left; empty
+        // keys match nothing
+        assert_eq!(
+            text(&join("outer")),
+            [
+                "1|Ann|A|pen",
+                "1|Ann|A|cap",
+                "2|Bob||",
+                "|Cy||",
+                "3|Di|D|ink",
+                "4||E|box",
+                "||F|mug"
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_joins() {
+        let j = Join::parse("Left: a = b, c").unwrap();
+        assert_eq!(j.kind, JoinKind::Left);
+        assert_eq!(j.name(), "left:a=b,c");
+        // A colon that doesn't follow a kind is part of a key
+        assert_eq!(Join::parse("Time:2").unwrap().keys[0].left, "Time:2");
+
+        assert!(Join::parse("inner:").is_err());
+        assert!(Join::parse("a=").is_err());
+
+        let mut j = Join::default();
+        assert!(j.set_suffixes("_a,_a").is_err());
+        assert!(j.set_suffixes("_a,_b").is_ok());
+        assert_eq!(j.suffixes, ("_a".to_string(), "_b".to_string()));
+    }
+
+    #[test]
+    fn unknown_key_column() {
+        let left = table(vec!["Id"], &[]);
+        let right = table(vec!["Key"], &[]);
+
+        assert_eq!(
+            left.join(right, &Join::parse("Id").unwrap()).err(),
+            Some("unknown right column \"Id\"".to_string())
+        );
+    }
+}
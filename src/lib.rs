@@ -1,4 +1,5 @@
 pub mod cell;
+pub mod html;
 pub mod row;
 pub mod table;
 pub mod workbook;
@@ -12,5 +13,23 @@ pub use workbook::*;
 pub use worksheet::*;
 pub use xml::*;
 
+pub mod aggregate;
+pub mod append;
+pub mod archive;
 pub mod builders;
+pub mod columns;
 pub mod convert;
+pub mod dedupe;
+pub mod dialect;
+pub mod directory;
+pub mod encoding;
+pub mod expr;
+pub mod feed;
+pub mod filetype;
+pub mod hash;
+pub mod infer;
+pub mod join;
+pub mod locale;
+pub mod media;
+pub mod sort;
+pub mod totals;
@@ -0,0 +1,312 @@
+use crate::{
+    cell::{Cell, NumberFormat},
+    infer::parse_number,
+};
+
+const CURRENCY_SYMBOLS: [char; 8] = ['$', '€', '£', '¥', '₹', '₽', '₩', '¢'];
+
+/// Decimal and digit grouping separators used to write numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberLocale {
+    pub decimal: char,
+    pub grouping: Vec<char>,
+}
+
+impl Default for NumberLocale {
+    fn default() -> NumberLocale {
+        NumberLocale {
+            decimal: '.',
+            grouping: vec![','],
+        }
+    }
+}
+
+/// A number read by [`NumberLocale::parse`], with the decorations it was
+/// written with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocaleNumber {
+    pub value: f64,
+    pub currency: Option<String>,
+    pub percent: bool,
+    /// Whether the number was written with grouping separators.
+    pub grouped: bool,
+    /// Number of digits after the decimal separator.
+    pub decimals: usize,
+}
+
+impl LocaleNumber {
+    /// Converts the number to a cell, formatted the way it was written.
+    pub fn to_cell(&self) -> Cell {
+        if let Some(symbol) = &self.currency {
+            Cell::FormattedNumber(self.value, NumberFormat::Currency(symbol.clone()))
+        } else if self.percent {
+            Cell::FormattedNumber(self.value / 100.0, NumberFormat::Percent)
+        } else if self.grouped {
+            Cell::FormattedNumber(
+                self.value,
+                NumberFormat::Grouped(self.decimals.min(15) as u8),
+            )
+        } else {
+            Cell::Number(self.value)
+        }
+    }
+}
+
+/// Splits a currency symbol (or three-letter code) from the front or back of
+/// an amount, returning the amount and the symbol.
+fn split_currency_symbol(value: &str) -> Option<(&str, String)> {
+    let is_code = |s: &str| s.len() == 3 && s.bytes().all(|b| b.is_ascii_uppercase());
+
+    if let Some(c) = value
+        .chars()
+        .next()
+        .filter(|c| CURRENCY_SYMBOLS.contains(c))
+    {
+        return Some((value[c.len_utf8()..].trim_start(), c.to_string()));
+    }
+    if let Some(c) = value
+        .chars()
+        .last()
+        .filter(|c| CURRENCY_SYMBOLS.contains(c))
+    {
+        return Some((
+            value[..value.len() - c.len_utf8()].trim_end(),
+            c.to_string(),
+        ));
+    }
+    if let Some((code, rest)) = value.split_once(' ').filter(|(c, _)| is_code(c)) {
+        return Some((rest.trim_start(), code.to_string()));
+    }
+    if let Some((rest, code)) = value.rsplit_once(' ').filter(|(_, c)| is_code(c)) {
+        return Some((rest.trim_end(), code.to_string()));
+    }
+
+    None
+}
+
+/// Removes a leading or trailing minus sign (ASCII or U+2212).
+fn strip_sign(value: &str) -> (bool, &str) {
+    const MINUS: [char; 2] = ['-', '−'];
+
+    if let Some(v) = value.strip_prefix(MINUS) {
+        (true, v.trim_start())
+    } else if let Some(v) = value.strip_suffix(MINUS) {
+        (true, v.trim_end())
+    } else {
+        (false, value)
+    }
+}
+
+impl NumberLocale {
+    /// Names accepted by [`NumberLocale::from_name`], besides custom pairs.
+    pub const PRESETS: [&'static str; 4] = ["en", "de", "fr", "ch"];
+
+    /// Looks up a locale by language or country code (e.g. `en`, `de`, `fr`,
+    /// `ch`), or takes two characters giving the decimal and grouping
+    /// separators, e.g. `,.` for "1.234,56".
+    pub fn from_name(name: &str) -> Option<NumberLocale> {
+        let (decimal, grouping) = match name.trim().to_ascii_lowercase().as_str() {
+            "en" | "us" | "gb" | "uk" | "au" | "ca" | "ie" | "ja" | "zh" | "ko" => ('.', vec![',']),
+            "de" | "at" | "es" | "it" | "nl" | "pt" | "br" | "da" | "id" | "tr" | "el" => {
+                (',', vec!['.'])
+            }
+            "fr" | "be" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk-ua" => {
+                (',', vec![' ', '\u{a0}', '\u{202f}'])
+            }
+            "ch" | "de-ch" | "li" => ('.', vec!['\'', '’']),
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next(), chars.next()) {
+                    (Some(d), Some(g), None) if d != g && !d.is_ascii_digit() => (d, vec![g]),
+                    _ => return None,
+                }
+            }
+        };
+
+        Some(NumberLocale { decimal, grouping })
+    }
+
+    /// Returns the preset name of the locale, or its separator pair.
+    pub fn name(&self) -> String {
+        NumberLocale::PRESETS
+            .into_iter()
+            .find(|p| NumberLocale::from_name(p).as_ref() == Some(self))
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| format!("{}{}", self.decimal, self.grouping[0]))
+    }
+
+    /// Writes 1234.56 with the locale's separators, e.g. "1.234,56".
+    pub fn example(&self) -> String {
+        format!("1{}234{}56", self.grouping[0], self.decimal)
+    }
+
+    /// Checks and joins the integer part of a number, which may be split into
+    /// groups of three digits by grouping separators.
+    fn integer_digits(&self, integer: &str) -> Option<(String, bool)> {
+        let groups: Vec<&str> = integer.split(|c| self.grouping.contains(&c)).collect();
+
+        if groups.len() == 1 {
+            return Some((integer.to_string(), false));
+        }
+
+        let valid = (1..=3).contains(&groups[0].len())
+            && groups[1..].iter().all(|g| g.len() == 3)
+            && groups.iter().all(|g| g.bytes().all(|b| b.is_ascii_digit()));
+
+        match valid {
+            true => Some((groups.concat(), true)),
+            false => None,
+        }
+    }
+
+    /// Parses a number such as "1.234,56", "€ 12,00", "12%", "(3.50)" or
+    /// "7-", returning `None` if the value isn't one.
+    pub fn parse(&self, value: &str) -> Option<LocaleNumber> {
+        let mut rest = value.trim();
+        let mut negative = false;
+
+        if let Some(inner) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+            negative = true;
+            rest = inner.trim();
+        }
+
+        let (sign, unsigned) = strip_sign(rest);
+        negative |= sign;
+        rest = unsigned;
+
+        let currency = match split_currency_symbol(rest) {
+            Some((amount, symbol)) => {
+                rest = amount;
+                Some(symbol)
+            }
+            None => None,
+        };
+
+        let (sign, unsigned) = strip_sign(rest);
+        negative |= sign;
+        rest = unsigned;
+
+        let percent = match rest.strip_suffix('%') {
+            Some(r) => {
+                rest = r.trim_end();
+                true
+            }
+            None => false,
+        };
+
+        if negative && rest.starts_with(['-', '+', '−']) {
+            return None;
+        }
+
+        // Exponents and the like, where the decimal separator is a point.
+        // The decimals are those written, before any exponent
+        if let Some(n) = parse_number(rest).filter(|_| self.decimal == '.') {
+            let mantissa = rest.split(['e', 'E']).next().unwrap_or(rest);
+            return Some(LocaleNumber {
+                value: if negative { -n } else { n },
+                currency,
+                percent,
+                grouped: false,
+                decimals: mantissa.split_once('.').map_or(0, |(_, f)| f.len()),
+            });
+        }
+
+        let (integer, fraction) = match rest.split_once(self.decimal) {
+            Some((i, f)) => (i, Some(f)),
+            None => (rest, None),
+        };
+
+        let (digits, grouped) = self.integer_digits(integer)?;
+        let plain = match fraction {
+            Some(f) => format!("{}.{}", digits, f),
+            None => digits,
+        };
+        let n = parse_number(&plain)?;
+
+        Some(LocaleNumber {
+            value: if negative { -n } else { n },
+            currency,
+            percent,
+            grouped,
+            decimals: fraction.map_or(0, |f| f.len()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponent_decimals() {
+        let en = NumberLocale::default();
+
+        let n = en.parse("1e-5").unwrap();
+        assert_eq!((n.value, n.decimals), (1e-5, 0));
+        let n = en.parse("1.5e3").unwrap();
+        assert_eq!((n.value, n.decimals), (1500.0, 1));
+        let n = en.parse("-2.25E+2").unwrap();
+        assert_eq!((n.value, n.decimals), (-225.0, 2));
+    }
+
+    fn value(locale: &str, text: &str) -> Option<f64> {
+        NumberLocale::from_name(locale)
+            .unwrap()
+            .parse(text)
+            .map(|n| n.value)
+    }
+
+    #[test]
+    fn locale_names() {
+        let de = NumberLocale::from_name(" DE ").unwrap();
+        assert_eq!((de.decimal, de.grouping.clone()), (',', vec!['.']));
+        assert_eq!(de.name(), "de");
+        assert_eq!(NumberLocale::from_name("at").unwrap().name(), "de");
+
+        let custom = NumberLocale::from_name(",_").unwrap();
+        assert_eq!((custom.decimal, custom.grouping.clone()), (',', vec!['_']));
+        assert_eq!(custom.name(), ",_");
+        assert_eq!(custom.example(), "1_234,56");
+
+        assert_eq!(NumberLocale::from_name(",,"), None);
+        assert_eq!(NumberLocale::from_name("1,"), None);
+        assert_eq!(NumberLocale::from_name("eng"), None);
+    }
+
+    #[test]
+    fn grouped_numbers() {
+        assert_eq!(value("en", "1,234,567.5"), Some(1234567.5));
+        assert_eq!(value("de", "1.234,56"), Some(1234.56));
+        assert_eq!(value("fr", "1\u{202f}234,5"), Some(1234.5));
+        assert_eq!(value("ch", "1'234.5"), Some(1234.5));
+        // Groups after the first must be three digits
+        assert_eq!(value("en", "12,34"), None);
+        assert_eq!(value("en", "1234,567"), None);
+        // A point isn't the decimal separator where the comma is
+        assert_eq!(value("de", "1.5"), None);
+
+        let n = NumberLocale::default().parse("1,234.50").unwrap();
+        assert!(n.grouped);
+        assert_eq!(n.decimals, 2);
+    }
+
+    #[test]
+    fn decorated_numbers() {
+        let en = NumberLocale::default();
+
+        let n = en.parse("€ 12.00").unwrap();
+        assert_eq!((n.value, n.currency.as_deref()), (12.0, Some("€")));
+        let n = en.parse("12.5 USD").unwrap();
+        assert_eq!((n.value, n.currency.as_deref()), (12.5, Some("USD")));
+        let n = en.parse("-$3").unwrap();
+        assert_eq!((n.value, n.currency.as_deref()), (-3.0, Some("$")));
+        let n = en.parse("12%").unwrap();
+        assert!(n.percent && n.value == 12.0);
+
+        assert_eq!(en.parse("(3.50)").map(|n| n.value), Some(-3.5));
+        assert_eq!(en.parse("7-").map(|n| n.value), Some(-7.0));
+        assert_eq!(en.parse("− 4").map(|n| n.value), Some(-4.0));
+        assert_eq!(en.parse("-+3"), None);
+        assert_eq!(en.parse("12 apples"), None);
+    }
+}
@@ -0,0 +1,1080 @@
+use chrono::NaiveDateTime;
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Properties of an image, audio or video file, read from its headers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    /// Format detected from the contents, e.g. "PNG" or "MP3".
+    pub format: Option<String>,
+    /// Width in pixels of an image or video.
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    /// When a photo was taken, from its EXIF data.
+    pub taken: Option<NaiveDateTime>,
+    /// Make and model of the camera, from EXIF data.
+    pub camera: Option<String>,
+    /// Length of audio or video, in seconds.
+    pub duration: Option<f64>,
+    /// Average bitrate of audio or video, in kilobits per second.
+    pub bitrate: Option<f64>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// Start of a file read to detect its format.
+const SNIFF_SIZE: u64 = 8 * 1024;
+
+/// Largest block of a file read in one go to find headers and tags.
+const HEAD_SIZE: u64 = 1024 * 1024;
+
+/// Deepest RIFF lists and MP4 boxes are read within each other. Those
+/// holding what is read lie a few levels down, and crafted files could
+/// otherwise nest them deep enough to overflow the stack.
+const MAX_NESTING: usize = 8;
+
+/// Reads the properties of a media file. Files of other types, and formats
+/// without a parser, give properties with just the format, if any.
+pub fn read_media(path: &Path) -> Result<MediaInfo, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let mut head = read_at(&mut file, 0, SNIFF_SIZE)?;
+
+    let Some(kind) = infer::get(&head) else {
+        return Ok(MediaInfo::default());
+    };
+    let mut info = MediaInfo {
+        format: Some(kind.extension().to_uppercase()),
+        ..MediaInfo::default()
+    };
+
+    // Formats with tags at the start are parsed from a larger block, while
+    // images and MP4 files are read as needed
+    let tagged = ["mp3", "flac", "ogg", "opus", "wav", "avi", "mkv", "webm"];
+    if tagged.contains(&kind.extension()) && size > SNIFF_SIZE {
+        head = read_at(&mut file, 0, HEAD_SIZE)?;
+    }
+
+    match kind.matcher_type() {
+        infer::MatcherType::Image => read_image(path, &mut info),
+        infer::MatcherType::Audio | infer::MatcherType::Video => match kind.extension() {
+            "mp3" => read_mp3(&mut file, &head, size, &mut info)?,
+            "flac" => read_flac(&head, &mut info),
+            "ogg" | "opus" => read_ogg(&mut file, &head, size, &mut info)?,
+            "wav" | "avi" => read_riff(&head, &mut info),
+            "mp4" | "m4a" | "m4v" | "mov" => read_mp4(&mut file, size, &mut info)?,
+            "mkv" | "webm" => read_matroska(&head, &mut info),
+            _ => (),
+        },
+        _ => (),
+    }
+
+    if info.bitrate.is_none() {
+        info.bitrate = info
+            .duration
+            .filter(|d| *d > 0.0)
+            .map(|d| size as f64 * 8.0 / d / 1000.0);
+    }
+
+    Ok(info)
+}
+
+/// Reads up to `length` bytes from `offset`.
+fn read_at(file: &mut File, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::new();
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.take(length).read_to_end(&mut buffer))
+        .map_err(|e| e.to_string())?;
+    Ok(buffer)
+}
+
+fn u32_be(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_be(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn u16_le(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_le(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Text of a tag, without padding, or `None` if blank.
+fn tag_text(text: &str) -> Option<String> {
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn read_image(path: &Path, info: &mut MediaInfo) {
+    if let Ok(size) = imagesize::size(path) {
+        info.width = Some(size.width as u64);
+        info.height = Some(size.height as u64);
+    }
+
+    let Ok(file) = File::open(path) else {
+        return;
+    };
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return;
+    };
+
+    let text = |tag: exif::Tag| match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => {
+            tag_text(&String::from_utf8_lossy(values.first()?.as_slice()))
+        }
+        _ => None,
+    };
+
+    info.taken = text(exif::Tag::DateTimeOriginal)
+        .or_else(|| text(exif::Tag::DateTime))
+        .and_then(|t| NaiveDateTime::parse_from_str(&t, "%Y:%m:%d %H:%M:%S").ok());
+    // Models often repeat the make, e.g. "Canon" and "Canon EOS 5D"
+    info.camera = match (text(exif::Tag::Make), text(exif::Tag::Model)) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+}
+
+/// Sets the title, artist and album from `KEY=VALUE` tags, as used by
+/// Vorbis comments.
+fn set_tag(info: &mut MediaInfo, tag: &str) {
+    let Some((key, value)) = tag.split_once('=') else {
+        return;
+    };
+
+    let field = match key.to_uppercase().as_str() {
+        "TITLE" => &mut info.title,
+        "ARTIST" => &mut info.artist,
+        "ALBUM" => &mut info.album,
+        _ => return,
+    };
+    if field.is_none() {
+        *field = tag_text(value);
+    }
+}
+
+/// Reads a Vorbis comment block: a vendor string then `KEY=VALUE` tags,
+/// each preceded by its little-endian length.
+fn read_vorbis_comment(data: &[u8], info: &mut MediaInfo) {
+    let Some(vendor) = u32_le(data, 0) else {
+        return;
+    };
+    let mut at = 4 + vendor as usize;
+    let Some(count) = u32_le(data, at) else {
+        return;
+    };
+    at += 4;
+
+    for _ in 0..count {
+        let Some(length) = u32_le(data, at) else {
+            return;
+        };
+        let Some(tag) = data.get(at + 4..at + 4 + length as usize) else {
+            return;
+        };
+        set_tag(info, &String::from_utf8_lossy(tag));
+        at += 4 + length as usize;
+    }
+}
+
+/// Decodes the text of an ID3v2 text frame, which starts with its encoding.
+fn id3_text(data: &[u8]) -> Option<String> {
+    let (encoding, text) = data.split_first()?;
+    let utf16 = |bytes: &[u8], big_endian: bool| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| match big_endian {
+                true => u16::from_be_bytes([c[0], c[1]]),
+                false => u16::from_le_bytes([c[0], c[1]]),
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    let text = match encoding {
+        0 => text.iter().map(|b| *b as char).collect(),
+        1 => match text {
+            [0xfe, 0xff, rest @ ..] => utf16(rest, true),
+            [0xff, 0xfe, rest @ ..] => utf16(rest, false),
+            _ => utf16(text, false),
+        },
+        2 => utf16(text, true),
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+    // Several values are separated by nulls; the first is enough
+    tag_text(text.split('\0').next().unwrap_or_default())
+}
+
+/// Reads the title, artist and album of an ID3v2 tag at the start of `data`,
+/// returning the tag's length.
+fn read_id3v2(data: &[u8], info: &mut MediaInfo) -> usize {
+    if !data.starts_with(b"ID3") || data.len() < 10 {
+        return 0;
+    }
+    let version = data[3];
+    let syncsafe = |at: usize| {
+        data.get(at..at + 4)
+            .map(|b| {
+                b.iter()
+                    .fold(0usize, |n, b| (n << 7) | (*b as usize & 0x7f))
+            })
+            .unwrap_or(0)
+    };
+    let length = 10 + syncsafe(6);
+    let tag = &data[..length.min(data.len())];
+
+    // Version 2 has 3-character frame IDs and 3-byte sizes
+    let (id_length, header_length) = match version {
+        2 => (3, 6),
+        _ => (4, 10),
+    };
+    // An extended header follows if flagged, its size counting itself only
+    // in version 4
+    let mut at = 10;
+    if data[5] & 0x40 != 0 {
+        at += match version {
+            3 => 4 + u32_be(tag, 10).unwrap_or(0) as usize,
+            _ => syncsafe(10),
+        };
+    }
+    while at + header_length <= tag.len() && tag[at] != 0 {
+        let id = &tag[at..at + id_length];
+        let size = match version {
+            2 => tag[at + 3..at + 6]
+                .iter()
+                .fold(0, |n, b| (n << 8) | *b as usize),
+            3 => u32_be(tag, at + 4).unwrap_or(0) as usize,
+            _ => syncsafe(at + 4),
+        };
+        let Some(frame) = tag.get(at + header_length..at + header_length + size) else {
+            break;
+        };
+
+        let field = match id {
+            b"TIT2" | b"TT2" => &mut info.title,
+            b"TPE1" | b"TP1" => &mut info.artist,
+            b"TALB" | b"TAL" => &mut info.album,
+            _ => {
+                at += header_length + size;
+                continue;
+            }
+        };
+        if field.is_none() {
+            *field = id3_text(frame);
+        }
+        at += header_length + size;
+    }
+
+    length
+}
+
+/// Bitrates in kbps of MPEG audio, by version 1 or 2, layer, then index.
+const MPEG_BITRATES: [[[u32; 15]; 3]; 2] = [
+    [
+        [
+            0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ],
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ],
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ],
+    ],
+    [
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ],
+];
+
+/// Sample rates of MPEG version 1 audio; version 2 halves and 2.5 quarters
+/// them.
+const MPEG_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+fn read_mp3(file: &mut File, head: &[u8], size: u64, info: &mut MediaInfo) -> Result<(), String> {
+    let start = read_id3v2(head, info) as u64;
+
+    // ID3v1 tags are the last 128 bytes: "TAG", then 30 bytes each of title,
+    // artist and album
+    let mut end = size;
+    let tail = read_at(file, size.saturating_sub(128), 128)?;
+    if tail.len() == 128 && tail.starts_with(b"TAG") {
+        end -= 128;
+        let field = |at: usize| {
+            tag_text(
+                &tail[at..at + 30]
+                    .iter()
+                    .map(|b| *b as char)
+                    .collect::<String>(),
+            )
+        };
+        info.title = info.title.take().or_else(|| field(3));
+        info.artist = info.artist.take().or_else(|| field(33));
+        info.album = info.album.take().or_else(|| field(63));
+    }
+
+    let frames = read_at(file, start, 64 * 1024)?;
+    read_mp3_frames(&frames, start, end, info);
+    Ok(())
+}
+
+/// Reads the duration and bitrate from the first MPEG audio frame in
+/// `data`, which starts at `offset` in the file, using its Xing or VBRI
+/// header if it has one and otherwise taking the bitrate as constant. `end`
+/// is where the audio ends in the file.
+fn read_mp3_frames(data: &[u8], offset: u64, end: u64, info: &mut MediaInfo) {
+    let Some(frame) = (0..data.len().saturating_sub(4))
+        .find(|i| data[*i] == 0xff && data[i + 1] & 0xe0 == 0xe0 && data[i + 2] & 0xf0 != 0xf0)
+    else {
+        return;
+    };
+    let header = &data[frame..];
+
+    // 3 is version 1, 2 version 2 and 0 version 2.5; 3 is layer I and 1
+    // layer III
+    let version = (header[1] >> 3) & 3;
+    let layer = (header[1] >> 1) & 3;
+    if version == 1 || layer == 0 {
+        return;
+    }
+    let bitrate_index = (header[2] >> 4) as usize;
+    let rate_index = ((header[2] >> 2) & 3) as usize;
+    let mono = header[3] >> 6 == 3;
+    let (Some(bitrate), Some(rate)) = (
+        MPEG_BITRATES[(version != 3) as usize][3 - layer as usize].get(bitrate_index),
+        MPEG_SAMPLE_RATES.get(rate_index),
+    ) else {
+        return;
+    };
+    let rate = match version {
+        3 => *rate,
+        2 => rate / 2,
+        _ => rate / 4,
+    };
+    let samples_per_frame = match (layer, version) {
+        (3, _) => 384,
+        (1, 2 | 0) => 576,
+        _ => 1152,
+    };
+
+    let side_info = match (version == 3, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let frames = match (header.get(4 + side_info..8 + side_info), header.get(36..40)) {
+        (Some(b"Xing" | b"Info"), _)
+            if u32_be(header, 8 + side_info).is_some_and(|f| f & 1 != 0) =>
+        {
+            u32_be(header, 12 + side_info)
+        }
+        (_, Some(b"VBRI")) => u32_be(header, 36 + 14),
+        _ => None,
+    };
+
+    let audio_bytes = end.saturating_sub(offset + frame as u64) as f64;
+    match frames {
+        Some(frames) if frames > 0 => {
+            let duration = frames as f64 * samples_per_frame as f64 / rate as f64;
+            info.duration = Some(duration);
+            info.bitrate = Some(audio_bytes * 8.0 / duration / 1000.0);
+        }
+        _ if *bitrate > 0 => {
+            info.duration = Some(audio_bytes * 8.0 / (*bitrate as f64 * 1000.0));
+            info.bitrate = Some(*bitrate as f64);
+        }
+        _ => (),
+    }
+}
+
+/// Reads a FLAC file's stream info and Vorbis comment metadata blocks.
+fn read_flac(data: &[u8], info: &mut MediaInfo) {
+    let mut at = 4;
+    while let Some(header) = u32_be(data, at) {
+        let last = header & 0x8000_0000 != 0;
+        let kind = (header >> 24) & 0x7f;
+        let length = (header & 0xff_ffff) as usize;
+        let Some(block) = data.get(at + 4..at + 4 + length) else {
+            return;
+        };
+
+        match kind {
+            // Sample rate is 20 bits from byte 10, and the sample count the
+            // 36 bits ending at byte 18
+            0 => {
+                let (Some(rate), Some(samples)) = (u32_be(block, 10), u64_be(block, 10)) else {
+                    return;
+                };
+                let rate = rate >> 12;
+                let samples = samples & 0xf_ffff_ffff;
+                if rate > 0 && samples > 0 {
+                    info.duration = Some(samples as f64 / rate as f64);
+                }
+            }
+            4 => read_vorbis_comment(block, info),
+            _ => (),
+        }
+
+        if last {
+            return;
+        }
+        at += 4 + length;
+    }
+}
+
+/// Joins the pages of an Ogg stream into its first few packets.
+fn ogg_packets(data: &[u8], count: usize) -> Vec<Vec<u8>> {
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut at = 0;
+
+    while data.get(at..at + 4) == Some(b"OggS") && packets.len() < count {
+        let Some(segments) = data.get(at + 26) else {
+            break;
+        };
+        let lacing_start = at + 27;
+        let Some(lacing) = data.get(lacing_start..lacing_start + *segments as usize) else {
+            break;
+        };
+
+        let mut body = lacing_start + lacing.len();
+        for length in lacing {
+            let Some(segment) = data.get(body..body + *length as usize) else {
+                return packets;
+            };
+            packet.extend_from_slice(segment);
+            body += *length as usize;
+            // Packets end with a segment shorter than 255 bytes
+            if *length < 255 {
+                packets.push(std::mem::take(&mut packet));
+            }
+        }
+        at = body;
+    }
+
+    packets
+}
+
+/// Reads the identification and comment headers of Ogg Vorbis and Opus
+/// files, and their duration from the position of the last page.
+fn read_ogg(file: &mut File, head: &[u8], size: u64, info: &mut MediaInfo) -> Result<(), String> {
+    let packets = ogg_packets(head, 2);
+    let (Some(id), Some(comment)) = (packets.first(), packets.get(1)) else {
+        return Ok(());
+    };
+
+    // Vorbis positions count samples, and Opus positions count samples at
+    // 48 kHz after the pre-skip
+    let (rate, skip) = if id.starts_with(b"\x01vorbis") {
+        info.format = Some("Ogg Vorbis".to_string());
+        if let Some(nominal) = u32_le(id, 20).filter(|b| *b as i32 > 0) {
+            info.bitrate = Some(nominal as f64 / 1000.0);
+        }
+        if let Some(tags) = comment.strip_prefix(b"\x03vorbis") {
+            read_vorbis_comment(tags, info);
+        }
+        (u32_le(id, 12).unwrap_or(0), 0)
+    } else if id.starts_with(b"OpusHead") {
+        info.format = Some("Ogg Opus".to_string());
+        if let Some(tags) = comment.strip_prefix(b"OpusTags") {
+            read_vorbis_comment(tags, info);
+        }
+        (48000, u16_le(id, 10).unwrap_or(0) as u64)
+    } else {
+        return Ok(());
+    };
+
+    let tail_start = size.saturating_sub(64 * 1024);
+    let tail = read_at(file, tail_start, 64 * 1024)?;
+    let last_page = tail.windows(4).rposition(|w| w == b"OggS");
+    if let Some(position) = last_page.and_then(|at| u64_le(&tail, at + 6)) {
+        if rate > 0 && position != u64::MAX {
+            info.duration = Some(position.saturating_sub(skip) as f64 / rate as f64);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the chunks of a RIFF file after the 12-byte file header: the
+/// format and length of a WAV file's audio and its INFO tags, or the main
+/// header of an AVI file.
+fn read_riff(data: &[u8], info: &mut MediaInfo) {
+    read_riff_chunks(data.get(12..).unwrap_or_default(), &mut None, info, 0);
+}
+
+/// Reads a run of RIFF chunks, and those in the lists among them, `depth`
+/// lists down.
+fn read_riff_chunks(data: &[u8], byte_rate: &mut Option<u32>, info: &mut MediaInfo, depth: usize) {
+    if depth > MAX_NESTING {
+        return;
+    }
+    let mut at = 0;
+    while let (Some(id), Some(length)) = (data.get(at..at + 4), u32_le(data, at + 4)) {
+        let body = at + 8;
+        let chunk = &data[body.min(data.len())..(body + length as usize).min(data.len())];
+
+        match id {
+            b"fmt " => *byte_rate = u32_le(chunk, 8).filter(|r| *r > 0),
+            b"data" => {
+                if let Some(rate) = byte_rate {
+                    info.duration = Some(length as f64 / *rate as f64);
+                    info.bitrate = Some(*rate as f64 * 8.0 / 1000.0);
+                }
+            }
+            b"LIST" => read_riff_chunks(
+                chunk.get(4..).unwrap_or_default(),
+                byte_rate,
+                info,
+                depth + 1,
+            ),
+            b"INAM" => info.title = tag_text(&String::from_utf8_lossy(chunk)),
+            b"IART" => info.artist = tag_text(&String::from_utf8_lossy(chunk)),
+            b"IPRD" => info.album = tag_text(&String::from_utf8_lossy(chunk)),
+            // Microseconds per frame, then the frame count at byte 16 and
+            // the size at byte 32
+            b"avih" => {
+                if let (Some(micros), Some(frames)) = (u32_le(chunk, 0), u32_le(chunk, 16)) {
+                    info.duration = Some(micros as f64 * frames as f64 / 1_000_000.0);
+                }
+                info.width = u32_le(chunk, 32).map(u64::from);
+                info.height = u32_le(chunk, 36).map(u64::from);
+            }
+            _ => (),
+        }
+
+        // Chunks are padded to an even length
+        at = body + length as usize + (length as usize & 1);
+    }
+}
+
+/// Finds the boxes of an MP4 file (or QuickTime movie) that hold its length,
+/// video size and tags, which all lie in the `moov` box.
+fn read_mp4(file: &mut File, size: u64, info: &mut MediaInfo) -> Result<(), String> {
+    let mut at = 0;
+    while at + 8 <= size {
+        let header = read_at(file, at, 16)?;
+        let (Some(length), Some(kind)) = (u32_be(&header, 0), header.get(4..8)) else {
+            break;
+        };
+        // Sizes of 1 are in 64 bits after the type, and 0 runs to the end
+        let (length, header_length) = match length {
+            0 => (size - at, 8),
+            1 => (u64_be(&header, 8).unwrap_or(0), 16),
+            l => (l as u64, 8),
+        };
+        if length < header_length {
+            break;
+        }
+
+        if kind == b"moov" {
+            let moov = read_at(file, at + header_length, length.min(16 * HEAD_SIZE))?;
+            read_mp4_boxes(&moov, info, 0);
+            break;
+        }
+        // Boxes claiming to run past what a file can hold end the search
+        let Some(next) = at.checked_add(length) else {
+            break;
+        };
+        at = next;
+    }
+
+    Ok(())
+}
+
+/// Reads the boxes within a box of an MP4 file, `depth` boxes down from
+/// `moov`.
+fn read_mp4_boxes(data: &[u8], info: &mut MediaInfo, depth: usize) {
+    if depth > MAX_NESTING {
+        return;
+    }
+    let mut at = 0;
+    while let (Some(length), Some(kind)) = (u32_be(data, at), data.get(at + 4..at + 8)) {
+        let length = match length {
+            0 => data.len() - at,
+            l => l as usize,
+        };
+        let Some(body) = data.get(at + 8..at + length.max(8)) else {
+            return;
+        };
+
+        match kind {
+            b"trak" | b"udta" | b"ilst" => read_mp4_boxes(body, info, depth + 1),
+            // A full box, with a version and flags before its boxes
+            b"meta" => read_mp4_boxes(body.get(4..).unwrap_or_default(), info, depth + 1),
+            // Version 1 has 64-bit times and duration
+            b"mvhd" => {
+                let (scale, duration) = match body.first() {
+                    Some(1) => (u32_be(body, 20), u64_be(body, 24)),
+                    _ => (u32_be(body, 12), u32_be(body, 16).map(u64::from)),
+                };
+                if let (Some(scale), Some(duration)) = (scale.filter(|s| *s > 0), duration) {
+                    info.duration = Some(duration as f64 / scale as f64);
+                }
+            }
+            // Width and height are 16.16 fixed point, at the end
+            b"tkhd" => {
+                let size_at = match body.first() {
+                    Some(1) => 88,
+                    _ => 76,
+                };
+                if let (Some(width), Some(height)) =
+                    (u32_be(body, size_at), u32_be(body, size_at + 4))
+                {
+                    if width > 0 && height > 0 && info.width.is_none() {
+                        info.width = Some(width as u64 >> 16);
+                        info.height = Some(height as u64 >> 16);
+                    }
+                }
+            }
+            // iTunes tags hold a "data" box with a type and locale before
+            // the text
+            b"\xa9nam" | b"\xa9ART" | b"\xa9alb" => {
+                let field = match kind {
+                    b"\xa9nam" => &mut info.title,
+                    b"\xa9ART" => &mut info.artist,
+                    _ => &mut info.album,
+                };
+                let data_length = u32_be(body, 0).unwrap_or(0) as usize;
+                if body.get(4..8) == Some(b"data") {
+                    *field = body
+                        .get(16..data_length.min(body.len()))
+                        .and_then(|t| tag_text(&String::from_utf8_lossy(t)));
+                }
+            }
+            _ => (),
+        }
+
+        at += length.max(8);
+    }
+}
+
+/// Reads an EBML variable-length integer, returning it and its length. IDs
+/// keep their length marker bits, and sizes don't.
+fn ebml_number(data: &[u8], at: usize, keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.get(at)?;
+    let length = first.leading_zeros() as usize + 1;
+    if length > 8 {
+        return None;
+    }
+
+    let mut value = match keep_marker {
+        true => first as u64,
+        false => (first as u64) & (0xff >> length),
+    };
+    for b in data.get(at + 1..at + length)? {
+        value = (value << 8) | *b as u64;
+    }
+    Some((value, length))
+}
+
+fn ebml_uint(data: &[u8]) -> u64 {
+    data.iter().fold(0, |n, b| (n << 8) | *b as u64)
+}
+
+/// Reads the segment information and video track size of a Matroska or WebM
+/// file, which come before its first cluster of frames.
+fn read_matroska(data: &[u8], info: &mut MediaInfo) {
+    const SEGMENT: u64 = 0x1853_8067;
+    const INFO: u64 = 0x1549_a966;
+    const TRACKS: u64 = 0x1654_ae6b;
+    const TRACK_ENTRY: u64 = 0xae;
+    const VIDEO: u64 = 0xe0;
+    const CLUSTER: u64 = 0x1f43_b675;
+    const TIMESTAMP_SCALE: u64 = 0x2a_d7b1;
+    const DURATION: u64 = 0x4489;
+    const TITLE: u64 = 0x7ba9;
+    const PIXEL_WIDTH: u64 = 0xb0;
+    const PIXEL_HEIGHT: u64 = 0xba;
+
+    let mut scale = 1_000_000;
+    let mut duration = None;
+    // Elements still to read within, with where they end
+    let mut parents = vec![data.len()];
+    let mut at = 0;
+
+    while let Some(end) = parents.last().copied() {
+        if at >= end {
+            parents.pop();
+            continue;
+        }
+        let Some((id, id_length)) = ebml_number(data, at, true) else {
+            break;
+        };
+        let Some((size, size_length)) = ebml_number(data, at + id_length, false) else {
+            break;
+        };
+        let body = at + id_length + size_length;
+        // Sizes of all ones are unknown, running to the end of the parent
+        let body_end = match size == (1 << (7 * size_length)) - 1 {
+            true => end,
+            false => body.saturating_add(size as usize).min(end),
+        };
+        // Elements whose header runs past the end of their parent have no value
+        let value = data.get(body..body_end).unwrap_or_default();
+
+        match id {
+            CLUSTER => break,
+            SEGMENT | INFO | TRACKS | TRACK_ENTRY | VIDEO => {
+                parents.push(body_end);
+                at = body;
+                continue;
+            }
+            TIMESTAMP_SCALE => scale = ebml_uint(value),
+            DURATION => {
+                duration = match value.len() {
+                    4 => Some(f32::from_be_bytes(value.try_into().unwrap_or_default()) as f64),
+                    8 => Some(f64::from_be_bytes(value.try_into().unwrap_or_default())),
+                    _ => None,
+                }
+            }
+            TITLE => info.title = tag_text(&String::from_utf8_lossy(value)),
+            PIXEL_WIDTH if info.width.is_none() => info.width = Some(ebml_uint(value)),
+            PIXEL_HEIGHT if info.height.is_none() => info.height = Some(ebml_uint(value)),
+            _ => (),
+        }
+        at = body_end;
+    }
+
+    // Durations count ticks of the timestamp scale, in nanoseconds
+    info.duration = duration.map(|d| d * scale as f64 / 1_000_000_000.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Reads the media properties of a file holding `data`.
+    fn read_bytes(name: &str, data: &[u8]) -> Result<MediaInfo, String> {
+        let path =
+            std::env::temp_dir().join(format!("sheet-gen-media-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        let info = read_media(&path);
+        fs::remove_file(&path).unwrap();
+        info
+    }
+
+    /// Runs a parser on every truncation of `data`, and on `data` with each
+    /// byte in turn replaced by 0x00 and 0xff, to check it never panics.
+    fn check_malformed(data: &[u8], parse: impl Fn(&[u8])) {
+        for length in 0..data.len() {
+            parse(&data[..length]);
+        }
+        for at in 0..data.len() {
+            for b in [0x00, 0xff] {
+                let mut broken = data.to_vec();
+                broken[at] = b;
+                parse(&broken);
+            }
+        }
+    }
+
+    fn riff_chunk(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((body.len() as u32).to_le_bytes());
+        chunk.extend(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// Half a second of 8-bit mono audio at 1600 Hz, titled "Beep".
+    fn wav() -> Vec<u8> {
+        let mut format = Vec::new();
+        for n in [1u16, 1] {
+            format.extend(n.to_le_bytes());
+        }
+        for n in [1600u32, 1600] {
+            format.extend(n.to_le_bytes());
+        }
+        for n in [1u16, 8] {
+            format.extend(n.to_le_bytes());
+        }
+        let mut list = b"INFO".to_vec();
+        list.extend(riff_chunk(b"INAM", b"Beep"));
+
+        let mut body = b"WAVE".to_vec();
+        body.extend(riff_chunk(b"fmt ", &format));
+        body.extend(riff_chunk(b"LIST", &list));
+        body.extend(riff_chunk(b"data", &[0x80; 800]));
+        riff_chunk(b"RIFF", &body)
+    }
+
+    /// Two seconds at 44.1 kHz, titled "Song".
+    fn flac() -> Vec<u8> {
+        let mut info = vec![0; 34];
+        let packed: u64 = (44100 << 44) | (1 << 41) | (15 << 36) | 88200;
+        info[10..18].copy_from_slice(&packed.to_be_bytes());
+
+        let mut comment = Vec::new();
+        comment.extend(4u32.to_le_bytes());
+        comment.extend(b"test");
+        comment.extend(1u32.to_le_bytes());
+        comment.extend(10u32.to_le_bytes());
+        comment.extend(b"TITLE=Song");
+
+        let mut data = b"fLaC".to_vec();
+        data.extend((34u32).to_be_bytes());
+        data.extend(info);
+        data.extend((0x8400_0000 | comment.len() as u32).to_be_bytes());
+        data.extend(comment);
+        data
+    }
+
+    fn mp4_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut b = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        b.extend(kind);
+        b.extend(body);
+        b
+    }
+
+    fn ftyp() -> Vec<u8> {
+        mp4_box(b"ftyp", b"isom\0\0\0\0isom")
+    }
+
+    /// A 640x360 movie lasting 12.5 seconds, titled "Clip".
+    fn moov() -> Vec<u8> {
+        let mut header = vec![0; 100];
+        header[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        header[16..20].copy_from_slice(&12500u32.to_be_bytes());
+        let mut track_header = vec![0; 84];
+        track_header[76..80].copy_from_slice(&(640u32 << 16).to_be_bytes());
+        track_header[80..84].copy_from_slice(&(360u32 << 16).to_be_bytes());
+
+        let title = mp4_box(b"\xa9nam", &mp4_box(b"data", b"\0\0\0\x01\0\0\0\0Clip"));
+        let mut meta = vec![0; 4];
+        meta.extend(mp4_box(b"ilst", &title));
+
+        let mut body = mp4_box(b"mvhd", &header);
+        body.extend(mp4_box(b"trak", &mp4_box(b"tkhd", &track_header)));
+        body.extend(mp4_box(b"udta", &mp4_box(b"meta", &meta)));
+        mp4_box(b"moov", &body)
+    }
+
+    fn id3v2() -> Vec<u8> {
+        let mut frame = b"TIT2".to_vec();
+        frame.extend(5u32.to_be_bytes());
+        frame.extend([0, 0, 0]);
+        frame.extend(b"Song");
+
+        let mut tag = b"ID3\x03\0\0".to_vec();
+        tag.extend([0, 0, 0, frame.len() as u8]);
+        tag.extend(frame);
+        tag
+    }
+
+    /// A second of MPEG 1 layer III at 128 kbps after an ID3v2 tag.
+    fn mp3() -> Vec<u8> {
+        let mut data = id3v2();
+        data.extend([0xff, 0xfb, 0x90, 0x00]);
+        data.extend(vec![0; 15996]);
+        data
+    }
+
+    fn ebml(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut element = id.to_vec();
+        element.push(0x80 | body.len() as u8);
+        element.extend(body);
+        element
+    }
+
+    /// A 640x360 video lasting 7.5 seconds, in a segment of unknown size.
+    fn matroska() -> Vec<u8> {
+        let mut info = ebml(&[0x2a, 0xd7, 0xb1], &[0x0f, 0x42, 0x40]);
+        info.extend(ebml(&[0x44, 0x89], &7500f64.to_be_bytes()));
+        info.extend(ebml(&[0x7b, 0xa9], b"Web"));
+        let mut video = ebml(&[0xb0], &[0x02, 0x80]);
+        video.extend(ebml(&[0xba], &[0x01, 0x68]));
+        let tracks = ebml(&[0xae], &ebml(&[0xe0], &video));
+
+        let mut data = vec![
+            0x18, 0x53, 0x80, 0x67, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ];
+        data.extend(ebml(&[0x15, 0x49, 0xa9, 0x66], &info));
+        data.extend(ebml(&[0x16, 0x54, 0xae, 0x6b], &tracks));
+        data.extend(ebml(&[0x1f, 0x43, 0xb6, 0x75], &[0; 16]));
+        data
+    }
+
+    #[test]
+    fn wav_properties() {
+        let info = read_bytes("wav", &wav()).unwrap();
+
+        assert_eq!(info.format.as_deref(), Some("WAV"));
+        assert_eq!(info.duration, Some(0.5));
+        assert_eq!(info.bitrate, Some(12.8));
+        assert_eq!(info.title.as_deref(), Some("Beep"));
+    }
+
+    #[test]
+    fn flac_properties() {
+        let info = read_bytes("flac", &flac()).unwrap();
+
+        assert_eq!(info.format.as_deref(), Some("FLAC"));
+        assert_eq!(info.duration, Some(2.0));
+        assert_eq!(info.title.as_deref(), Some("Song"));
+    }
+
+    #[test]
+    fn mp4_properties() {
+        let mut data = ftyp();
+        data.extend(mp4_box(b"free", &[0; 8]));
+        data.extend(moov());
+        let info = read_bytes("mp4", &data).unwrap();
+
+        assert_eq!(info.format.as_deref(), Some("MP4"));
+        assert_eq!(info.duration, Some(12.5));
+        assert_eq!((info.width, info.height), (Some(640), Some(360)));
+        assert_eq!(info.title.as_deref(), Some("Clip"));
+    }
+
+    #[test]
+    fn mp4_box_size_overflow() {
+        // A box with a 64-bit size reaching past the largest offset
+        let mut data = ftyp();
+        data.extend(1u32.to_be_bytes());
+        data.extend(b"free");
+        data.extend((u64::MAX - 8).to_be_bytes());
+        data.extend(moov());
+        let info = read_bytes("mp4-overflow", &data).unwrap();
+
+        assert_eq!(info.format.as_deref(), Some("MP4"));
+        assert_eq!(info.duration, None);
+    }
+
+    #[test]
+    fn mp3_properties() {
+        let data = mp3();
+        let mut info = MediaInfo::default();
+        let start = read_id3v2(&data, &mut info);
+        read_mp3_frames(&data[start..], start as u64, data.len() as u64, &mut info);
+
+        assert_eq!(info.title.as_deref(), Some("Song"));
+        assert_eq!(info.bitrate, Some(128.0));
+        assert_eq!(info.duration, Some(1.0));
+    }
+
+    #[test]
+    fn matroska_properties() {
+        let mut info = MediaInfo::default();
+        read_matroska(&matroska(), &mut info);
+
+        assert_eq!(info.duration, Some(7.5));
+        assert_eq!((info.width, info.height), (Some(640), Some(360)));
+        assert_eq!(info.title.as_deref(), Some("Web"));
+    }
+
+    #[test]
+    fn matroska_child_past_parent() {
+        // Info is one byte long, but holds the header of a longer duration
+        let data = [0x15, 0x49, 0xa9, 0x66, 0x81, 0x44, 0x89, 0x84, 0, 0, 0, 0];
+        let mut info = MediaInfo::default();
+        read_matroska(&data, &mut info);
+
+        assert_eq!(info.duration, None);
+    }
+
+    /// `content` within `levels` of containers, each a header made by
+    /// `header` from the length of what follows it.
+    fn nested(levels: usize, content: &[u8], header: impl Fn(usize) -> Vec<u8>) -> Vec<u8> {
+        let mut headers = Vec::new();
+        let mut length = content.len();
+        for _ in 0..levels {
+            let h = header(length);
+            length += h.len();
+            headers.push(h);
+        }
+
+        let mut data: Vec<u8> = headers.into_iter().rev().flatten().collect();
+        data.extend(content);
+        data
+    }
+
+    #[test]
+    fn deeply_nested() {
+        // Lists and boxes nested far deeper than any file needs, ending in
+        // what would otherwise be read
+        let list = nested(100_000, &riff_chunk(b"INAM", b"Deep"), |length| {
+            let mut h = b"LIST".to_vec();
+            h.extend((length as u32 + 4).to_le_bytes());
+            h.extend(b"INFO");
+            h
+        });
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend(list);
+        let mut info = MediaInfo::default();
+        read_riff(&wav, &mut info);
+        assert_eq!(info.title, None);
+
+        let trak = nested(100_000, &moov()[8..], |length| {
+            let mut h = (length as u32 + 8).to_be_bytes().to_vec();
+            h.extend(b"trak");
+            h
+        });
+        read_mp4_boxes(&trak, &mut info, 0);
+        assert_eq!(info.duration, None);
+
+        // A few levels are still read
+        let mut info = MediaInfo::default();
+        read_mp4_boxes(
+            &nested(3, &moov()[8..], |length| {
+                let mut h = (length as u32 + 8).to_be_bytes().to_vec();
+                h.extend(b"trak");
+                h
+            }),
+            &mut info,
+            0,
+        );
+        assert_eq!(info.duration, Some(12.5));
+    }
+
+    #[test]
+    fn malformed_headers() {
+        check_malformed(&wav(), |d| read_riff(d, &mut MediaInfo::default()));
+        check_malformed(&flac(), |d| read_flac(d, &mut MediaInfo::default()));
+        check_malformed(&moov(), |d| read_mp4_boxes(d, &mut MediaInfo::default(), 0));
+        check_malformed(&matroska(), |d| read_matroska(d, &mut MediaInfo::default()));
+        check_malformed(&id3v2(), |d| {
+            read_id3v2(d, &mut MediaInfo::default());
+        });
+        check_malformed(&mp3()[..64], |d| {
+            read_mp3_frames(d, 0, 16000, &mut MediaInfo::default())
+        });
+        // The comment block follows the stream info block and its header
+        check_malformed(&flac()[46..], |d| {
+            read_vorbis_comment(d, &mut MediaInfo::default())
+        });
+    }
+
+    #[test]
+    fn truncated_files() {
+        let mut mp4 = ftyp();
+        mp4.extend(moov());
+
+        for (name, data) in [("wav", wav()), ("flac", flac()), ("mp4", mp4)] {
+            for length in (0..data.len()).step_by(7) {
+                let name = format!("{}-{}", name, length);
+                assert!(read_bytes(&name, &data[..length]).is_ok());
+            }
+        }
+    }
+}
@@ -3,11 +3,16 @@ use crate::cell::*;
 #[derive(Default, Clone)]
 pub struct Row {
     pub cells: Vec<Cell>,
+    /// Style of the row's cells, e.g. `Total`, in place of the table's.
+    pub style: Option<String>,
 }
 
 impl Row {
     pub fn new() -> Row {
-        Row { cells: Vec::new() }
+        Row {
+            cells: Vec::new(),
+            style: None,
+        }
     }
 
     pub fn with_cells(mut self, cells: Vec<Cell>) -> Row {
@@ -15,6 +20,11 @@ impl Row {
         self
     }
 
+    pub fn with_style(mut self, style_id: &str) -> Row {
+        self.style = Some(style_id.to_string());
+        self
+    }
+
     pub fn from_cells(cells: Vec<Cell>) -> Row {
         Row::new().with_cells(cells)
     }
@@ -24,7 +34,7 @@ impl Row {
             "<Row>\n{}</Row>",
             self.cells
                 .iter()
-                .map(|c| (c.to_xml() + "\n"))
+                .map(|c| c.to_xml() + "\n")
                 .collect::<String>()
         )
     }
@@ -34,7 +44,7 @@ impl Row {
             "<Row>\n{}</Row>",
             self.cells
                 .iter()
-                .map(|c| (c.to_xml_with_style(style_id) + "\n"))
+                .map(|c| c.to_xml_with_style(style_id) + "\n")
                 .collect::<String>()
         )
     }
@@ -0,0 +1,293 @@
+use chrono::NaiveDateTime;
+use std::cmp::Ordering;
+
+use crate::{
+    cell::Cell,
+    infer::{parse_date, parse_number},
+    table::Table,
+};
+
+/// How the values of a sort column are compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    /// Numbers, then dates, then text, each read from text where possible.
+    Auto,
+    /// Text, ignoring case.
+    Text,
+    Number,
+    Date,
+    /// Text with runs of digits compared as numbers, so "file2" comes before
+    /// "file10".
+    Natural,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 5] = [
+        SortMode::Auto,
+        SortMode::Text,
+        SortMode::Number,
+        SortMode::Date,
+        SortMode::Natural,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Auto => "auto",
+            SortMode::Text => "text",
+            SortMode::Number => "number",
+            SortMode::Date => "date",
+            SortMode::Natural => "natural",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SortMode> {
+        SortMode::ALL
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// A column rows are sorted by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    /// Heading or 1-based index of the column.
+    pub column: String,
+    pub descending: bool,
+    pub mode: SortMode,
+    /// Whether empty values go before the others, whichever the direction.
+    pub empty_first: bool,
+}
+
+impl SortKey {
+    pub fn new(column: &str) -> SortKey {
+        SortKey {
+            column: column.to_string(),
+            descending: false,
+            mode: SortMode::Auto,
+            empty_first: false,
+        }
+    }
+
+    /// Parses `COLUMN[:FLAG...]`, where the flags are `asc` or `desc`, a
+    /// [`SortMode`] name, and `empty-first` or `empty-last`.
+    pub fn parse(spec: &str) -> Result<SortKey, String> {
+        let mut column = spec.trim();
+        let mut key = SortKey::new("");
+
+        // Flags are taken from the end, so headings may contain colons
+        while let Some((rest, flag)) = column.rsplit_once(':') {
+            match flag.trim().to_ascii_lowercase().as_str() {
+                "asc" => key.descending = false,
+                "desc" => key.descending = true,
+                "empty-first" => key.empty_first = true,
+                "empty-last" => key.empty_first = false,
+                f => match SortMode::from_name(f) {
+                    Some(m) => key.mode = m,
+                    None => break,
+                },
+            }
+            column = rest.trim();
+        }
+
+        if column.is_empty() {
+            return Err(format!("expected COLUMN[:FLAG...], got \"{}\"", spec));
+        }
+
+        key.column = column.to_string();
+        Ok(key)
+    }
+
+    /// Parses a comma-separated list of sort keys.
+    pub fn parse_list(list: &str) -> Result<Vec<SortKey>, String> {
+        list.split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(SortKey::parse)
+            .collect()
+    }
+
+    /// Formats the key the way [`SortKey::parse`] reads it, leaving out
+    /// default flags.
+    pub fn name(&self) -> String {
+        let mut name = self.column.clone();
+
+        if self.descending {
+            name.push_str(":desc");
+        }
+        if self.mode != SortMode::Auto {
+            name = format!("{}:{}", name, self.mode.name());
+        }
+        if self.empty_first {
+            name.push_str(":empty-first");
+        }
+
+        name
+    }
+
+    /// Reads a cell as a value to sort by, or `None` if it is empty.
+    pub fn sort_value(&self, cell: Option<&Cell>) -> Option<SortValue> {
+        let cell = match cell {
+            Some(Cell::Formula(_, v)) => Some(v.as_ref()),
+            c => c,
+        };
+        let text = match cell? {
+            Cell::Empty => return None,
+            c => c.to_string(),
+        };
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        let number = || match cell? {
+            Cell::Number(n) | Cell::FormattedNumber(n, _) => Some(*n),
+            Cell::Boolean(b) => Some(*b as u8 as f64),
+            Cell::Text(s) => parse_number(s.trim()),
+            _ => None,
+        };
+        let date = || match cell? {
+            Cell::DateTime(d) => Some(*d),
+            Cell::Text(s) => parse_date(s.trim(), None),
+            _ => None,
+        };
+        let as_text = || SortValue::Text(text.to_lowercase(), text.clone());
+
+        Some(match self.mode {
+            SortMode::Auto => number()
+                .map(SortValue::Number)
+                .or_else(|| date().map(SortValue::Date))
+                .unwrap_or_else(as_text),
+            SortMode::Number => number().map_or_else(as_text, SortValue::Number),
+            SortMode::Date => date().map_or_else(as_text, SortValue::Date),
+            SortMode::Text => as_text(),
+            SortMode::Natural => SortValue::Natural(natural_chunks(&text), text),
+        })
+    }
+
+    /// Orders two values read by [`SortKey::sort_value`].
+    pub fn compare(&self, a: &Option<SortValue>, b: &Option<SortValue>) -> Ordering {
+        match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if self.empty_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => self.compare(b, a).reverse(),
+            (Some(a), Some(b)) if self.descending => b.cmp(a),
+            (Some(a), Some(b)) => a.cmp(b),
+        }
+    }
+}
+
+/// Part of a value compared in natural order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Chunk {
+    /// A run of digits, without leading zeros, ordered by length first.
+    Digits(usize, String),
+    Text(String),
+}
+
+fn natural_chunks(text: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let digits = c.is_ascii_digit();
+        let len = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(len);
+
+        chunks.push(match digits {
+            true => {
+                let trimmed = chunk.trim_start_matches('0');
+                Chunk::Digits(trimmed.len(), trimmed.to_string())
+            }
+            false => Chunk::Text(chunk.to_lowercase()),
+        });
+        rest = tail;
+    }
+
+    chunks
+}
+
+/// A cell read for sorting. Values of different kinds sort numbers first,
+/// then dates, then text.
+#[derive(Debug, Clone)]
+pub enum SortValue {
+    Number(f64),
+    Date(NaiveDateTime),
+    /// Lowercase text, then the original text to break ties.
+    Text(String, String),
+    Natural(Vec<Chunk>, String),
+}
+
+impl SortValue {
+    fn rank(&self) -> u8 {
+        match self {
+            SortValue::Number(_) => 0,
+            SortValue::Date(_) => 1,
+            SortValue::Text(..) => 2,
+            SortValue::Natural(..) => 3,
+        }
+    }
+}
+
+impl PartialEq for SortValue {
+    fn eq(&self, other: &SortValue) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortValue {}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &SortValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &SortValue) -> Ordering {
+        match (self, other) {
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Date(a), SortValue::Date(b)) => a.cmp(b),
+            (SortValue::Text(a, x), SortValue::Text(b, y)) => (a, x).cmp(&(b, y)),
+            (SortValue::Natural(a, x), SortValue::Natural(b, y)) => (a, x).cmp(&(b, y)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Table {
+    /// Sorts the rows by the given keys, earlier keys taking precedence. Rows
+    /// that compare equal keep their order.
+    pub fn sort(&mut self, keys: &[SortKey]) -> Result<(), String> {
+        let indices = keys
+            .iter()
+            .map(|k| {
+                self.column_index(&k.column)
+                    .ok_or(format!("unknown column \"{}\"", k.column))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        let mut rows: Vec<_> = std::mem::take(&mut self.rows)
+            .into_iter()
+            .map(|r| {
+                let values: Vec<_> = keys
+                    .iter()
+                    .zip(&indices)
+                    .map(|(k, i)| k.sort_value(r.cells.get(*i)))
+                    .collect();
+                (values, r)
+            })
+            .collect();
+
+        rows.sort_by(|(a, _), (b, _)| {
+            keys.iter()
+                .zip(a.iter().zip(b))
+                .map(|(k, (a, b))| k.compare(a, b))
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        self.rows = rows.into_iter().map(|(_, r)| r).collect();
+        Ok(())
+    }
+}
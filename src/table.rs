@@ -1,7 +1,12 @@
-use crate::{cell::Cell, row::*};
+use crate::{
+    cell::{Cell, NumberFormat},
+    row::*,
+};
 
 #[derive(Default, Clone)]
 pub struct Table {
+    /// Rows written above the headings, e.g. a block of source metadata.
+    pub preamble: Vec<Row>,
     pub headings: Option<Vec<String>>,
     pub rows: Vec<Row>,
 }
@@ -9,6 +14,7 @@ pub struct Table {
 impl Table {
     pub fn new() -> Table {
         Table {
+            preamble: Vec::new(),
             rows: Vec::new(),
             headings: None,
         }
@@ -28,9 +34,63 @@ impl Table {
         Table::new().with_rows(rows)
     }
 
+    /// Number of columns: the most of the headings and any row.
+    pub fn width(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|r| r.cells.len())
+            .chain(self.headings.iter().map(|h| h.len()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Finds a column by heading (ignoring case if there is no exact match),
+    /// or else by 1-based index.
+    pub fn column_index(&self, column: &str) -> Option<usize> {
+        let column = column.trim();
+
+        self.headings
+            .as_ref()
+            .and_then(|h| {
+                h.iter()
+                    .position(|h| h == column)
+                    .or_else(|| h.iter().position(|h| h.eq_ignore_ascii_case(column)))
+            })
+            .or_else(|| {
+                column
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|i| (1..=self.width()).contains(i))
+                    .map(|i| i - 1)
+            })
+    }
+
+    /// Number formats used by the table's cells, each paired with the style
+    /// of the row it appears in.
+    pub fn number_formats(&self) -> Vec<(String, NumberFormat)> {
+        let mut formats: Vec<(String, NumberFormat)> = Vec::new();
+
+        for row in self.preamble.iter().chain(self.rows.iter()) {
+            let style = row.style.as_deref().unwrap_or("Default");
+
+            for f in row.cells.iter().filter_map(|c| c.number_format()) {
+                let pair = (style.to_string(), f);
+                if !formats.contains(&pair) {
+                    formats.push(pair);
+                }
+            }
+        }
+
+        formats
+    }
+
     pub fn to_xml(&self) -> String {
         format!(
-            "<Table>\n{}{}</Table>",
+            "<Table>\n{}{}{}</Table>",
+            self.preamble
+                .iter()
+                .map(|r| r.to_xml_with_style("Default") + "\n")
+                .collect::<String>(),
             match self.headings.clone() {
                 Some(vec) =>
                     Row::from_cells(vec.iter().map(|label| Cell::Text(label.clone())).collect())
@@ -40,7 +100,7 @@ impl Table {
             },
             self.rows
                 .iter()
-                .map(|r| (r.to_xml_with_style("Default") + "\n"))
+                .map(|r| r.to_xml_with_style(r.style.as_deref().unwrap_or("Default")) + "\n")
                 .collect::<String>()
         )
     }
@@ -0,0 +1,313 @@
+use crate::{
+    aggregate::{cell_number, Aggregate, AggregateFunction},
+    cell::{Cell, Formula, FormulaPart},
+    row::Row,
+    table::Table,
+};
+
+/// Totals added below a table's rows, and subtotals within them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Totals {
+    /// Function totalling numeric columns that have none of their own.
+    pub function: AggregateFunction,
+    /// Functions for particular columns, by heading or 1-based index.
+    pub columns: Vec<(String, AggregateFunction)>,
+    /// Column whose changes of value insert subtotal rows.
+    pub subtotal_by: Option<String>,
+    /// Whether totals are written as `SUBTOTAL` formulas, so they follow
+    /// edits and filters in the spreadsheet.
+    pub formulas: bool,
+}
+
+impl Default for Totals {
+    fn default() -> Totals {
+        Totals {
+            function: AggregateFunction::Sum,
+            columns: Vec::new(),
+            subtotal_by: None,
+            formulas: false,
+        }
+    }
+}
+
+/// Number of the `SUBTOTAL` function computing an aggregate, if any.
+fn subtotal_function_number(function: AggregateFunction) -> Option<u8> {
+    match function {
+        AggregateFunction::Average => Some(1),
+        AggregateFunction::Count => Some(3),
+        AggregateFunction::Max => Some(4),
+        AggregateFunction::Min => Some(5),
+        AggregateFunction::Sum => Some(9),
+        AggregateFunction::Distinct => None,
+    }
+}
+
+fn parse_total_function(name: &str) -> Result<AggregateFunction, String> {
+    match AggregateFunction::from_name(name) {
+        Some(f) if subtotal_function_number(f).is_some() => Ok(f),
+        _ => Err(format!(
+            "unknown total function \"{}\", expected sum, avg, count, min or max",
+            name.trim()
+        )),
+    }
+}
+
+impl Totals {
+    /// Reads `FUNCTION[,COLUMN=FUNCTION...]` into the functions, where the
+    /// first applies to the numeric columns not listed.
+    pub fn set_functions(&mut self, list: &str) -> Result<(), String> {
+        self.columns.clear();
+
+        for item in list.split(',').filter(|s| !s.trim().is_empty()) {
+            match item.rsplit_once('=') {
+                Some((column, function)) => self
+                    .columns
+                    .push((column.trim().to_string(), parse_total_function(function)?)),
+                None => self.function = parse_total_function(item)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Formats the functions the way [`Totals::set_functions`] reads them.
+    pub fn functions_name(&self) -> String {
+        std::iter::once(self.function.name().to_string())
+            .chain(
+                self.columns
+                    .iter()
+                    .map(|(c, f)| format!("{}={}", c, f.name())),
+            )
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+/// Whether every non-empty cell of a column is a number, and there is one.
+fn is_numeric_column(rows: &[Row], index: usize) -> bool {
+    let mut cells = rows
+        .iter()
+        .filter_map(|r| r.cells.get(index))
+        .filter(|c| !matches!(c, Cell::Empty))
+        .peekable();
+
+    cells.peek().is_some()
+        && cells.all(|c| match c {
+            Cell::Text(_) => false,
+            c => cell_number(c).is_some(),
+        })
+}
+
+/// Makes a row of totals over `rows`, which are the `above` rows directly
+/// above it when written as formulas.
+fn total_row(
+    functions: &[Option<AggregateFunction>],
+    rows: &[&Row],
+    above: usize,
+    formulas: bool,
+) -> Row {
+    Row::from_cells(
+        functions
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let Some(function) = f else {
+                    return Cell::Empty;
+                };
+                let value = Aggregate {
+                    function: *function,
+                    column: None,
+                }
+                .compute(rows, Some(i));
+
+                match (formulas && above > 0, subtotal_function_number(*function)) {
+                    (true, Some(n)) => Cell::Formula(
+                        Formula {
+                            parts: vec![FormulaPart::Text(format!(
+                                "SUBTOTAL({},R[-{}]C:R[-1]C)",
+                                n, above
+                            ))],
+                        },
+                        Box::new(value),
+                    ),
+                    _ => value,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Appends a styled totals row to a table, with subtotal rows after each run
+/// of rows with the same value in the subtotal column.
+pub fn add_totals(mut table: Table, totals: &Totals) -> Result<Table, String> {
+    let column = |c: &String| {
+        table
+            .column_index(c)
+            .ok_or(format!("unknown column \"{}\"", c))
+    };
+
+    let subtotal_index = totals.subtotal_by.as_ref().map(column).transpose()?;
+    let mut functions: Vec<Option<AggregateFunction>> = (0..table.width())
+        .map(|i| is_numeric_column(&table.rows, i).then_some(totals.function))
+        .collect();
+    for (c, f) in &totals.columns {
+        functions[column(c)?] = Some(*f);
+    }
+
+    // Labels go in the subtotal column, or the first one without a total
+    let label_index = subtotal_index
+        .filter(|i| functions[*i].is_none())
+        .or_else(|| functions.iter().position(|f| f.is_none()));
+    let labelled = |mut row: Row, label: String, style: &str| {
+        if let Some(i) = label_index {
+            row.cells[i] = Cell::Text(label);
+        }
+        row.with_style(style)
+    };
+
+    let data = std::mem::take(&mut table.rows);
+    let mut rows: Vec<Row> = Vec::new();
+
+    if let Some(index) = subtotal_index {
+        let key = |r: &Row| {
+            r.cells
+                .get(index)
+                .map(|c| c.to_string())
+                .unwrap_or_default()
+        };
+        let mut start = 0;
+
+        for end in 1..=data.len() {
+            if end < data.len() && key(&data[end]) == key(&data[start]) {
+                continue;
+            }
+
+            let run: Vec<&Row> = data[start..end].iter().collect();
+            rows.extend(data[start..end].iter().cloned());
+            rows.push(labelled(
+                total_row(&functions, &run, run.len(), totals.formulas),
+                format!("{} Total", key(&data[start])),
+                "Subtotal",
+            ));
+            start = end;
+        }
+    } else {
+        rows.extend(data.iter().cloned());
+    }
+
+    let all: Vec<&Row> = data.iter().collect();
+    let label = match subtotal_index {
+        Some(_) => "Grand Total",
+        None => "Total",
+    };
+    rows.push(labelled(
+        total_row(&functions, &all, rows.len(), totals.formulas),
+        label.to_string(),
+        "Total",
+    ));
+
+    table.rows = rows;
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let row = |region: &str, name: &str, amount: f64| {
+            Row::from_cells(vec![
+                Cell::Text(region.to_string()),
+                Cell::Text(name.to_string()),
+                Cell::Number(amount),
+            ])
+        };
+
+        Table::new()
+            .with_headings(vec!["Region", "Name", "Amount"])
+            .with_rows(vec![
+                row("North", "Ann", 10.0),
+                row("North", "Bob", 5.0),
+                row("South", "Cy", 2.0),
+            ])
+    }
+
+    fn text(table: &Table) -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|r| {
+                r.cells
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn totals_numeric_columns() {
+        let t = add_totals(table(), &Totals::default()).unwrap();
+
+        assert_eq!(text(&t)[3], "Total||17");
+        assert_eq!(t.rows[3].style.as_deref(), Some("Total"));
+    }
+
+    #[test]
+    fn subtotals() {
+        let mut totals = Totals {
+            subtotal_by: Some("region".to_string()),
+            ..Totals::default()
+        };
+        totals.set_functions("avg,Name=count").unwrap();
+        assert_eq!(totals.functions_name(), "avg,Name=count");
+
+        let t = add_totals(table(), &totals).unwrap();
+        assert_eq!(
+            text(&t),
+            [
+                "North|Ann|10",
+                "North|Bob|5",
+                "North Total|2|7.5",
+                "South|Cy|2",
+                "South Total|1|2",
+                "Grand Total|3|5.666666666666667",
+            ]
+        );
+        let styles: Vec<_> = t.rows.iter().map(|r| r.style.as_deref()).collect();
+        assert_eq!(styles[2], Some("Subtotal"));
+        assert_eq!(styles[5], Some("Total"));
+    }
+
+    #[test]
+    fn subtotal_formulas() {
+        let totals = Totals {
+            subtotal_by: Some("1".to_string()),
+            formulas: true,
+            ..Totals::default()
+        };
+        let t = add_totals(table(), &totals).unwrap();
+
+        let formula = |row: usize| match &t.rows[row].cells[2] {
+            Cell::Formula(f, v) => match f.parts.as_slice() {
+                [FormulaPart::Text(text)] => format!("{} = {}", text, v),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+        assert_eq!(formula(2), "SUBTOTAL(9,R[-2]C:R[-1]C) = 15");
+        assert_eq!(formula(4), "SUBTOTAL(9,R[-1]C:R[-1]C) = 2");
+        assert_eq!(formula(5), "SUBTOTAL(9,R[-5]C:R[-1]C) = 17");
+    }
+
+    #[test]
+    fn rejects_unknown_functions_and_columns() {
+        let mut totals = Totals::default();
+        assert!(totals.set_functions("distinct").is_err());
+        assert!(totals.set_functions("Amount=median").is_err());
+
+        totals.set_functions("Missing=sum").unwrap();
+        assert!(add_totals(table(), &totals).is_err());
+    }
+}
@@ -19,13 +19,34 @@ impl Workbook {
         self
     }
 
+    /// Styles applying number formats on top of the header's styles.
+    fn number_format_styles(&self) -> String {
+        let mut formats = Vec::new();
+
+        for pair in self
+            .worksheets
+            .iter()
+            .flat_map(|w| w.table.number_formats())
+        {
+            if !formats.contains(&pair) {
+                formats.push(pair);
+            }
+        }
+
+        formats
+            .iter()
+            .map(|(parent, f)| f.style_xml(parent) + "\n")
+            .collect()
+    }
+
     pub fn to_xml(&self) -> String {
         format!(
-            "{}\n{}</Workbook>",
+            "{}\n{}</Styles>\n{}</Workbook>",
             WORKBOOK_HEADER,
+            self.number_format_styles(),
             self.worksheets
                 .iter()
-                .map(|w| (w.to_xml() + "\n"))
+                .map(|w| w.to_xml() + "\n")
                 .collect::<String>()
         )
     }
@@ -1,5 +1,67 @@
 use crate::{table::Table, xml};
 
+/// Longest sheet name Excel accepts.
+pub const MAX_SHEET_NAME: usize = 31;
+
+/// Characters of the title a sheet name keeps, however long its suffix.
+const MIN_TITLE: usize = 8;
+
+/// Name for a new sheet from a title and a suffix to follow it, e.g.
+/// "(Summary)": without the characters Excel doesn't allow in sheet names,
+/// with the title (and if need be the suffix) cut short so the name fits in
+/// 31 characters, and numbered if one of the sheets already has the name
+/// (ignoring case).
+pub fn sheet_name(title: &str, suffix: &str, sheets: &[Worksheet]) -> String {
+    let clean = |s: &str| {
+        s.chars()
+            .filter(|c| !"[]:*?/\\".contains(*c))
+            .collect::<String>()
+            .trim_matches(|c: char| c == '\'' || c.is_whitespace())
+            .to_string()
+    };
+    let title = match clean(title) {
+        t if t.is_empty() => "Sheet".to_string(),
+        t => t,
+    };
+    let suffix = clean(suffix);
+    let cut = |s: &str, length: usize| s.chars().take(length).collect::<String>();
+    let join = |parts: [&str; 2]| {
+        parts
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    };
+
+    (1..)
+        .map(|n| {
+            let number = match n {
+                1 => String::new(),
+                n => format!("({})", n),
+            };
+            // The suffix gives way to the number and the start of the title
+            let mut kept = title.chars().count().min(MIN_TITLE) + 1;
+            if !number.is_empty() {
+                kept += number.len() + 1;
+            }
+            let suffix = cut(&suffix, MAX_SHEET_NAME.saturating_sub(kept));
+            let tail = join([suffix.trim_end(), &number]);
+
+            let room = match tail.is_empty() {
+                true => MAX_SHEET_NAME,
+                false => MAX_SHEET_NAME.saturating_sub(tail.chars().count() + 1),
+            };
+            let name = join([cut(&title, room).trim_end(), &tail]);
+            cut(&name, MAX_SHEET_NAME).trim_end().to_string()
+        })
+        .find(|name| {
+            !sheets
+                .iter()
+                .any(|s| s.name.to_lowercase() == name.to_lowercase())
+        })
+        .unwrap_or_default()
+}
+
 #[derive(Default, Clone)]
 pub struct Worksheet {
     pub name: String,
@@ -32,3 +94,76 @@ impl Worksheet {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names sheets one after another from the same title and suffix.
+    fn names(title: &str, suffix: &str, count: usize) -> Vec<String> {
+        let mut sheets = Vec::new();
+        for _ in 0..count {
+            let name = sheet_name(title, suffix, &sheets);
+            sheets.push(Worksheet::new().with_name(&name));
+        }
+        sheets.into_iter().map(|s| s.name).collect()
+    }
+
+    #[test]
+    fn cleans_and_cuts_names() {
+        assert_eq!(sheet_name("Q1: a/b [draft]?", "", &[]), "Q1 ab draft");
+        assert_eq!(sheet_name("'Quoted'", "", &[]), "Quoted");
+        assert_eq!(
+            sheet_name("An extremely long worksheet title", "(Summary)", &[]),
+            "An extremely long wor (Summary)"
+        );
+    }
+
+    #[test]
+    fn numbers_repeated_names() {
+        assert_eq!(
+            names("Sales", "by Region", 3),
+            [
+                "Sales by Region",
+                "Sales by Region (2)",
+                "Sales by Region (3)"
+            ]
+        );
+        // Excel compares sheet names ignoring case
+        let sheets = vec![Worksheet::new().with_name("SALES")];
+        assert_eq!(sheet_name("Sales", "", &sheets), "Sales (2)");
+    }
+
+    #[test]
+    fn long_suffix_collision() {
+        let suffix = "by Region and Quarter of the Financial Year";
+        let names = names("Sales", suffix, 3);
+
+        assert_eq!(names[0], "Sales by Region and Quarter of");
+        assert_eq!(names[1], "Sales by Region and Quarter (2)");
+        assert_eq!(names[2], "Sales by Region and Quarter (3)");
+    }
+
+    #[test]
+    fn long_title_and_suffix() {
+        let names = names(&"t".repeat(40), "(Channel, long-file-name.xml)", 12);
+
+        for name in &names {
+            assert!(name.chars().count() <= MAX_SHEET_NAME, "{}", name);
+            assert!(name.starts_with("tttttttt "), "{}", name);
+        }
+        assert!(names[11].ends_with("(12)"));
+    }
+
+    #[test]
+    fn empty_title() {
+        assert_eq!(sheet_name("", "", &[]), "Sheet");
+        assert_eq!(sheet_name("[]", "(Summary)", &[]), "Sheet (Summary)");
+
+        let names = names("", &"s".repeat(30), 2);
+        assert_eq!(names[0].chars().count(), MAX_SHEET_NAME);
+        assert!(names[0].starts_with("Sheet s"));
+        assert_eq!(names[1].chars().count(), MAX_SHEET_NAME);
+        assert!(names[1].ends_with(" (2)"));
+    }
+}